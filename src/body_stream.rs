@@ -0,0 +1,52 @@
+//! Incremental reading of fetch response bodies
+//!
+//! Streaming consumers (Server-Sent Events, line-delimited JSON) read the
+//! response body chunk by chunk through the `ReadableStream` reader instead of
+//! waiting for the whole body.
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use crate::HttpError;
+
+/// Reader over the chunks of a response body
+pub(crate) struct BodyReader {
+    reader: web_sys::ReadableStreamDefaultReader,
+}
+
+impl BodyReader {
+    /// Lock the body of `response` for reading
+    pub(crate) fn new(response: &reqwasm::http::Response) -> Result<Self, HttpError> {
+        let body = response.body().ok_or(HttpError::InvalidResponse)?;
+        Ok(Self {
+            reader: body.get_reader().unchecked_into(),
+        })
+    }
+
+    /// Next chunk of the body, or `None` once the body is complete
+    pub(crate) async fn next_chunk(&self) -> Result<Option<Vec<u8>>, HttpError> {
+        let result = JsFuture::from(self.reader.read()).await.map_err(|e| HttpError::Network {
+            message: format!("Failed to read response stream: {:?}", e),
+        })?;
+
+        let done = js_sys::Reflect::get(&result, &"done".into())
+            .map(|done| done.is_truthy())
+            .unwrap_or(true);
+        if done {
+            return Ok(None);
+        }
+
+        let value = js_sys::Reflect::get(&result, &"value".into()).map_err(|_| HttpError::InvalidResponse)?;
+        Ok(Some(js_sys::Uint8Array::new(&value).to_vec()))
+    }
+
+    /// Stop reading and release the underlying connection
+    pub(crate) fn cancel(&self) {
+        let _ = self.reader.cancel();
+    }
+}
+
+impl Drop for BodyReader {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
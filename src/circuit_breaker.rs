@@ -0,0 +1,305 @@
+//! Circuit breaker that fails fast while a backend is down
+//!
+//! Failures (network errors, timeouts and 5xx responses) are tracked per host,
+//! or per registered path prefix. After `failure_threshold` consecutive
+//! failures the circuit opens and requests fail immediately with
+//! [`HttpError::CircuitOpen`]. Once `cooldown_ms` has passed a single probe
+//! request is let through (half-open); its outcome closes or re-opens the circuit.
+//! The probe is held by a [`CircuitPermit`], so a probe that is dropped before
+//! completing lets the next request probe instead.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use url::Url;
+use yew::Callback;
+use crate::HttpError;
+
+/// State of a single circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests fail fast until the cooldown has passed
+    Open,
+    /// A probe request is checking whether the backend recovered
+    HalfOpen,
+}
+
+#[derive(Debug, Clone)]
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at_ms: f64,
+    probe_in_flight: bool,
+    probe_started_ms: f64,
+}
+
+impl Default for Circuit {
+    fn default() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            opened_at_ms: 0.0,
+            probe_in_flight: false,
+            probe_started_ms: 0.0,
+        }
+    }
+}
+
+struct CircuitBreakerState {
+    failure_threshold: u32,
+    cooldown_ms: u32,
+    path_prefixes: Vec<String>,
+    circuits: HashMap<String, Circuit>,
+    on_state_change: Option<Callback<(String, CircuitState)>>,
+}
+
+/// Circuit breaker shared by all clones
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    state: Rc<RefCell<CircuitBreakerState>>,
+}
+
+impl CircuitBreaker {
+    /// Open a circuit after `failure_threshold` consecutive failures for `cooldown_ms`
+    pub fn new(failure_threshold: u32, cooldown_ms: u32) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(CircuitBreakerState {
+                failure_threshold: failure_threshold.max(1),
+                cooldown_ms,
+                path_prefixes: Vec::new(),
+                circuits: HashMap::new(),
+                on_state_change: None,
+            })),
+        }
+    }
+
+    /// Address of the shared state, the same for every clone
+    pub(crate) fn identity(&self) -> usize {
+        Rc::as_ptr(&self.state) as usize
+    }
+
+    /// Track requests whose path starts with `prefix` in their own circuit
+    ///
+    /// Requests not matching any prefix are tracked per host.
+    pub fn path_prefix(self, prefix: &str) -> Self {
+        self.state.borrow_mut().path_prefixes.push(prefix.to_string());
+        self
+    }
+
+    /// Get notified whenever a circuit changes state, e.g. to show a status banner
+    pub fn on_state_change<F>(self, handler: F) -> Self
+    where
+        F: Fn(&str, CircuitState) + 'static,
+    {
+        self.state.borrow_mut().on_state_change =
+            Some(Callback::from(move |(key, state): (String, CircuitState)| handler(&key, state)));
+        self
+    }
+
+    /// Circuit key for a request URL
+    pub fn key_for(&self, url: &str) -> String {
+        let (origin, path) = split_origin(url);
+        let state = self.state.borrow();
+
+        match state.path_prefixes.iter().find(|prefix| path.starts_with(prefix.as_str())) {
+            Some(prefix) => format!("{}{}", origin, prefix),
+            None => origin,
+        }
+    }
+
+    /// Current state of the circuit handling `url`
+    pub fn state(&self, url: &str) -> CircuitState {
+        let key = self.key_for(url);
+        self.state.borrow().circuits.get(&key).map(|c| c.state).unwrap_or(CircuitState::Closed)
+    }
+
+    /// States of all tracked circuits
+    pub fn states(&self) -> Vec<(String, CircuitState)> {
+        self.state.borrow().circuits.iter().map(|(k, c)| (k.clone(), c.state)).collect()
+    }
+
+    /// Check whether a request to `url` may be sent
+    ///
+    /// The returned permit records the request's outcome; dropping it
+    /// without recording releases a half-open probe.
+    pub fn check(&self, url: &str, now_ms: f64) -> Result<CircuitPermit, HttpError> {
+        let key = self.key_for(url);
+        let mut state = self.state.borrow_mut();
+        let cooldown_ms = state.cooldown_ms as f64;
+        let circuit = state.circuits.entry(key.clone()).or_default();
+
+        let (changed, probe) = match circuit.state {
+            CircuitState::Closed => (false, false),
+            CircuitState::Open => {
+                let elapsed = now_ms - circuit.opened_at_ms;
+                if elapsed < cooldown_ms {
+                    return Err(HttpError::CircuitOpen {
+                        key,
+                        retry_after_ms: (cooldown_ms - elapsed).ceil() as u32,
+                    });
+                }
+                circuit.state = CircuitState::HalfOpen;
+                (true, true)
+            }
+            CircuitState::HalfOpen => {
+                if circuit.probe_in_flight {
+                    // Ask callers to come back once the probe should have finished
+                    let elapsed = now_ms - circuit.probe_started_ms;
+                    return Err(HttpError::CircuitOpen {
+                        key,
+                        retry_after_ms: (cooldown_ms - elapsed).ceil().max(1.0) as u32,
+                    });
+                }
+                (false, true)
+            }
+        };
+        if probe {
+            circuit.probe_in_flight = true;
+            circuit.probe_started_ms = now_ms;
+        }
+
+        drop(state);
+        if changed {
+            self.notify(&key, CircuitState::HalfOpen);
+        }
+        Ok(CircuitPermit {
+            breaker: self.clone(),
+            key,
+            probe,
+            recorded: false,
+        })
+    }
+
+    /// Record the outcome of a request that passed [`check`](Self::check)
+    fn record<T>(&self, key: &str, result: &Result<T, HttpError>, now_ms: f64) {
+        match result {
+            Ok(_) => self.record_success(key),
+            Err(error) if is_failure(error) => self.record_failure(key, now_ms),
+            // Neither success nor backend failure: let another probe through
+            Err(_) => self.release_probe(key),
+        }
+    }
+
+    fn release_probe(&self, key: &str) {
+        if let Some(circuit) = self.state.borrow_mut().circuits.get_mut(key) {
+            circuit.probe_in_flight = false;
+        }
+    }
+
+    fn record_success(&self, key: &str) {
+        let previous = {
+            let mut state = self.state.borrow_mut();
+            let circuit = state.circuits.entry(key.to_string()).or_default();
+            let previous = circuit.state;
+            *circuit = Circuit::default();
+            previous
+        };
+
+        if previous != CircuitState::Closed {
+            self.notify(key, CircuitState::Closed);
+        }
+    }
+
+    fn record_failure(&self, key: &str, now_ms: f64) {
+        let opened = {
+            let mut state = self.state.borrow_mut();
+            let threshold = state.failure_threshold;
+            let circuit = state.circuits.entry(key.to_string()).or_default();
+            circuit.consecutive_failures += 1;
+            circuit.probe_in_flight = false;
+
+            let should_open = match circuit.state {
+                CircuitState::HalfOpen => true,
+                CircuitState::Closed => circuit.consecutive_failures >= threshold,
+                CircuitState::Open => false,
+            };
+            if should_open {
+                circuit.state = CircuitState::Open;
+                circuit.opened_at_ms = now_ms;
+            }
+            should_open
+        };
+
+        if opened {
+            self.notify(key, CircuitState::Open);
+        }
+    }
+
+    fn notify(&self, key: &str, circuit_state: CircuitState) {
+        let handler = self.state.borrow().on_state_change.clone();
+        if let Some(handler) = handler {
+            handler.emit((key.to_string(), circuit_state));
+        }
+    }
+}
+
+impl fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("CircuitBreaker")
+            .field("failure_threshold", &state.failure_threshold)
+            .field("cooldown_ms", &state.cooldown_ms)
+            .field("path_prefixes", &state.path_prefixes)
+            .field("circuits", &state.circuits)
+            .finish()
+    }
+}
+
+/// Whether an error indicates the backend is failing
+fn is_failure(error: &HttpError) -> bool {
+    match error {
+        HttpError::Network { .. } | HttpError::Timeout => true,
+        HttpError::Http { status, .. } => *status >= 500,
+        _ => false,
+    }
+}
+
+/// Split a URL into its origin (empty for relative URLs) and path
+fn split_origin(url: &str) -> (String, String) {
+    match Url::parse(url) {
+        Ok(parsed) => (parsed.origin().ascii_serialization(), parsed.path().to_string()),
+        Err(_) => (String::new(), url.split(['?', '#']).next().unwrap_or_default().to_string()),
+    }
+}
+
+/// Permission to send one request through a [`CircuitBreaker`]
+///
+/// Record the request's outcome with [`record`](Self::record). A half-open
+/// probe whose permit is dropped unrecorded (e.g. because the request future
+/// was cancelled) is released so the circuit does not stay stuck.
+#[derive(Debug)]
+pub struct CircuitPermit {
+    breaker: CircuitBreaker,
+    key: String,
+    probe: bool,
+    recorded: bool,
+}
+
+impl CircuitPermit {
+    /// Key of the circuit this request belongs to
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Whether this request is the half-open probe
+    pub fn is_probe(&self) -> bool {
+        self.probe
+    }
+
+    /// Record the outcome of the request
+    pub fn record<T>(mut self, result: &Result<T, HttpError>, now_ms: f64) {
+        self.recorded = true;
+        self.breaker.record(&self.key, result, now_ms);
+    }
+}
+
+impl Drop for CircuitPermit {
+    fn drop(&mut self) {
+        if self.probe && !self.recorded {
+            self.breaker.release_probe(&self.key);
+        }
+    }
+}
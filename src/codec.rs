@@ -0,0 +1,247 @@
+//! Pluggable body codecs
+//!
+//! A [`Codec`] turns serde values into request bodies and response bodies back
+//! into values. JSON is always available; MessagePack and CBOR are enabled by
+//! the `msgpack` and `cbor` features. [`HttpResponse::decode`] picks the codec
+//! matching the response `Content-Type`.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::{HttpError, HttpResponse, RequestBody, RequestBuilder};
+
+/// Media type of a `Content-Type` value, lowercased and without parameters
+pub fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Encoding and decoding of bodies in one format
+pub trait Codec {
+    /// `Content-Type` sent with encoded bodies
+    fn content_type(&self) -> &'static str;
+
+    /// Whether this codec can decode bodies of the given media type
+    fn accepts(&self, media_type: &str) -> bool {
+        media_type == self.content_type()
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, HttpError>;
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, HttpError>;
+}
+
+/// `application/json`, also accepting `+json` media types
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        media_type == "application/json" || media_type.ends_with("+json")
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, HttpError> {
+        serde_json::to_vec(value).map_err(|e| HttpError::Serialization {
+            message: format!("Failed to serialize JSON: {}", e),
+        })
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, HttpError> {
+        serde_json::from_slice(bytes).map_err(|e| HttpError::Serialization {
+            message: format!("Failed to deserialize JSON: {}", e),
+        })
+    }
+}
+
+/// `application/msgpack`; structs are encoded as maps so fields are matched by name
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgPackCodec;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MsgPackCodec {
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        matches!(media_type, "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack")
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, HttpError> {
+        rmp_serde::to_vec_named(value).map_err(|e| HttpError::Serialization {
+            message: format!("Failed to serialize MessagePack: {}", e),
+        })
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, HttpError> {
+        rmp_serde::from_slice(bytes).map_err(|e| HttpError::Serialization {
+            message: format!("Failed to deserialize MessagePack: {}", e),
+        })
+    }
+}
+
+/// `application/cbor`, also accepting `+cbor` media types
+#[cfg(feature = "cbor")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor")]
+impl Codec for CborCodec {
+    fn content_type(&self) -> &'static str {
+        "application/cbor"
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        media_type == "application/cbor" || media_type.ends_with("+cbor")
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, HttpError> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes).map_err(|e| HttpError::Serialization {
+            message: format!("Failed to serialize CBOR: {}", e),
+        })?;
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, HttpError> {
+        ciborium::from_reader(bytes).map_err(|e| HttpError::Serialization {
+            message: format!("Failed to deserialize CBOR: {}", e),
+        })
+    }
+}
+
+/// The codecs built into the crate, used for content negotiation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinCodec {
+    Json,
+    #[cfg(feature = "msgpack")]
+    MsgPack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl BuiltinCodec {
+    /// All enabled codecs, in order of preference
+    pub fn all() -> Vec<BuiltinCodec> {
+        vec![
+            BuiltinCodec::Json,
+            #[cfg(feature = "msgpack")]
+            BuiltinCodec::MsgPack,
+            #[cfg(feature = "cbor")]
+            BuiltinCodec::Cbor,
+        ]
+    }
+
+    /// Codec able to decode a body with the given `Content-Type`
+    pub fn for_content_type(content_type: &str) -> Option<BuiltinCodec> {
+        let media_type = media_type(content_type);
+        BuiltinCodec::all().into_iter().find(|codec| codec.accepts(&media_type))
+    }
+
+    /// `Accept` header value listing every enabled codec
+    pub fn accept_header() -> String {
+        BuiltinCodec::all()
+            .iter()
+            .map(|codec| codec.content_type())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Codec for BuiltinCodec {
+    fn content_type(&self) -> &'static str {
+        match self {
+            BuiltinCodec::Json => JsonCodec.content_type(),
+            #[cfg(feature = "msgpack")]
+            BuiltinCodec::MsgPack => MsgPackCodec.content_type(),
+            #[cfg(feature = "cbor")]
+            BuiltinCodec::Cbor => CborCodec.content_type(),
+        }
+    }
+
+    fn accepts(&self, media_type: &str) -> bool {
+        match self {
+            BuiltinCodec::Json => JsonCodec.accepts(media_type),
+            #[cfg(feature = "msgpack")]
+            BuiltinCodec::MsgPack => MsgPackCodec.accepts(media_type),
+            #[cfg(feature = "cbor")]
+            BuiltinCodec::Cbor => CborCodec.accepts(media_type),
+        }
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, HttpError> {
+        match self {
+            BuiltinCodec::Json => JsonCodec.encode(value),
+            #[cfg(feature = "msgpack")]
+            BuiltinCodec::MsgPack => MsgPackCodec.encode(value),
+            #[cfg(feature = "cbor")]
+            BuiltinCodec::Cbor => CborCodec.encode(value),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, HttpError> {
+        match self {
+            BuiltinCodec::Json => JsonCodec.decode(bytes),
+            #[cfg(feature = "msgpack")]
+            BuiltinCodec::MsgPack => MsgPackCodec.decode(bytes),
+            #[cfg(feature = "cbor")]
+            BuiltinCodec::Cbor => CborCodec.decode(bytes),
+        }
+    }
+}
+
+impl RequestBuilder {
+    /// Encode the body with `codec` and set its `Content-Type`
+    pub fn body_with<C: Codec, T: Serialize + ?Sized>(mut self, codec: C, data: &T) -> Result<Self, HttpError> {
+        let bytes = codec.encode(data)?;
+        self.config.headers.insert("Content-Type".to_string(), codec.content_type().to_string());
+        self.config.body = RequestBody::Binary(bytes);
+        Ok(self)
+    }
+
+    /// Advertise every enabled codec in the `Accept` header
+    pub fn accept_codecs(self) -> Self {
+        self.header("Accept", &BuiltinCodec::accept_header())
+    }
+}
+
+impl HttpResponse {
+    /// Decode the body with `codec`
+    pub fn decode_with<C: Codec, T: DeserializeOwned>(&self, codec: C) -> Result<T, HttpError> {
+        codec.decode(&self.bytes)
+    }
+
+    /// Decode the body with the codec matching its `Content-Type`
+    ///
+    /// A missing `Content-Type`, or one no enabled codec accepts, is a
+    /// [`HttpError::Serialization`] error; use [`HttpResponse::decode_or`] to
+    /// pick a codec for untyped responses.
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        let content_type = self.header("Content-Type").ok_or_else(|| HttpError::Serialization {
+            message: "Response has no Content-Type to pick a codec from".to_string(),
+        })?;
+        self.codec_for(content_type)?.decode(&self.bytes)
+    }
+
+    /// Like [`HttpResponse::decode`], but decode responses without a `Content-Type` with `fallback`
+    pub fn decode_or<C: Codec, T: DeserializeOwned>(&self, fallback: C) -> Result<T, HttpError> {
+        match self.header("Content-Type") {
+            Some(content_type) => self.codec_for(content_type)?.decode(&self.bytes),
+            None => fallback.decode(&self.bytes),
+        }
+    }
+
+    fn codec_for(&self, content_type: &str) -> Result<BuiltinCodec, HttpError> {
+        BuiltinCodec::for_content_type(content_type).ok_or_else(|| HttpError::Serialization {
+            message: format!("No codec for content type {}", content_type),
+        })
+    }
+}
@@ -0,0 +1,172 @@
+//! Request body compression
+//!
+//! [`RequestBuilder::compress`] compresses text, JSON and binary bodies with
+//! gzip or deflate before sending and sets `Content-Encoding`. The browser's
+//! `CompressionStream` is used when available, with a pure Rust encoder from
+//! the default `compression-fallback` feature as the fallback. Bodies smaller
+//! than the policy's minimum size are sent as-is, since compressing them
+//! rarely pays for the overhead.
+
+#[cfg(feature = "compression-fallback")]
+use std::io::Write;
+#[cfg(feature = "compression-fallback")]
+use flate2::write::{GzEncoder, ZlibEncoder};
+#[cfg(feature = "compression-fallback")]
+use flate2::Compression;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use crate::{HttpError, RequestBody, RequestBuilder};
+
+/// Bodies below this many bytes are not compressed by default
+pub const DEFAULT_MIN_SIZE: usize = 1024;
+
+/// Content coding applied to request bodies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    /// zlib-wrapped deflate, as HTTP's `deflate` coding specifies
+    Deflate,
+}
+
+impl Encoding {
+    /// Value of the `Content-Encoding` header
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// How and from which size request bodies are compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionPolicy {
+    pub encoding: Encoding,
+    /// Smallest body size, in bytes, that gets compressed
+    pub min_size: usize,
+}
+
+impl CompressionPolicy {
+    /// Whether a body of `len` bytes should be compressed
+    pub fn applies_to(&self, len: usize) -> bool {
+        len >= self.min_size
+    }
+}
+
+/// Raw bytes of bodies that can be compressed
+pub(crate) fn body_bytes(body: &RequestBody) -> Option<&[u8]> {
+    match body {
+        RequestBody::Text(text) | RequestBody::Json(text) => Some(text.as_bytes()),
+        RequestBody::Binary(data) => Some(data),
+        _ => None,
+    }
+}
+
+/// Compress `data` with the pure Rust encoder
+#[cfg(feature = "compression-fallback")]
+pub fn compress_bytes(data: &[u8], encoding: Encoding) -> Result<Vec<u8>, HttpError> {
+    let map_err = |e: std::io::Error| HttpError::Serialization {
+        message: format!("Failed to compress request body: {}", e),
+    };
+
+    match encoding {
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).map_err(map_err)?;
+            encoder.finish().map_err(map_err)
+        }
+        Encoding::Deflate => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).map_err(map_err)?;
+            encoder.finish().map_err(map_err)
+        }
+    }
+}
+
+/// Compress `data` with the browser's `CompressionStream`, if it exists
+///
+/// Looked up at runtime since not every browser supports it yet. Returns
+/// `None` when it is missing or fails, so the caller can fall back.
+async fn compress_in_browser(data: &[u8], encoding: Encoding) -> Option<Vec<u8>> {
+    if !cfg!(target_arch = "wasm32") {
+        return None;
+    }
+    let constructor = js_sys::Reflect::get(&js_sys::global(), &"CompressionStream".into())
+        .ok()?
+        .dyn_into::<js_sys::Function>()
+        .ok()?;
+    let args = js_sys::Array::of1(&encoding.as_str().into());
+    let transform = js_sys::Reflect::construct(&constructor, &args).ok()?;
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
+    let blob = web_sys::Blob::new_with_u8_array_sequence(&parts).ok()?;
+    let compressed = blob.stream().pipe_through(transform.unchecked_ref());
+
+    let response = web_sys::Response::new_with_opt_readable_stream(Some(&compressed)).ok()?;
+    let buffer = JsFuture::from(response.array_buffer().ok()?).await.ok()?;
+    Some(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+#[cfg(feature = "compression-fallback")]
+fn compress_fallback(data: &[u8], encoding: Encoding) -> Result<Vec<u8>, HttpError> {
+    compress_bytes(data, encoding)
+}
+
+#[cfg(not(feature = "compression-fallback"))]
+fn compress_fallback(_data: &[u8], encoding: Encoding) -> Result<Vec<u8>, HttpError> {
+    Err(HttpError::Configuration {
+        message: format!(
+            "No {} encoder: CompressionStream is unavailable and the compression-fallback feature is disabled",
+            encoding.as_str()
+        ),
+    })
+}
+
+/// Compress `data`, preferring the browser's native implementation
+///
+/// Fails with [`HttpError::Configuration`] when the browser has no
+/// `CompressionStream` and the `compression-fallback` feature is disabled.
+pub async fn compress(data: &[u8], encoding: Encoding) -> Result<Vec<u8>, HttpError> {
+    match compress_in_browser(data, encoding).await {
+        Some(compressed) => Ok(compressed),
+        None => compress_fallback(data, encoding),
+    }
+}
+
+impl RequestBuilder {
+    /// Compress the body with `encoding` if it is at least [`DEFAULT_MIN_SIZE`] bytes
+    ///
+    /// Sending fails with [`HttpError::Configuration`] if the body needs
+    /// compressing but the browser has no `CompressionStream` and the default
+    /// `compression-fallback` feature is disabled.
+    pub fn compress(self, encoding: Encoding) -> Self {
+        self.compress_above(encoding, DEFAULT_MIN_SIZE)
+    }
+
+    /// Compress the body with `encoding` if it is at least `min_size` bytes
+    pub fn compress_above(mut self, encoding: Encoding, min_size: usize) -> Self {
+        self.config.compression = Some(CompressionPolicy { encoding, min_size });
+        self
+    }
+
+    /// Replace the body with its compressed form and set `Content-Encoding`
+    ///
+    /// Skipped for small or non-compressible bodies and when a
+    /// `Content-Encoding` was already set; fails when no encoder is available.
+    pub(crate) async fn apply_compression(&mut self) -> Result<(), HttpError> {
+        let policy = match self.config.compression {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+        let already_encoded = self.config.headers.keys().any(|name| name.eq_ignore_ascii_case("Content-Encoding"));
+        let data = match body_bytes(&self.config.body) {
+            Some(data) if !already_encoded && policy.applies_to(data.len()) => data,
+            _ => return Ok(()),
+        };
+
+        let compressed = compress(data, policy.encoding).await?;
+        self.config.headers.insert("Content-Encoding".to_string(), policy.encoding.as_str().to_string());
+        self.config.body = RequestBody::Binary(compressed);
+        Ok(())
+    }
+}
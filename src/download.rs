@@ -0,0 +1,311 @@
+//! Range requests and resumable downloads
+//!
+//! A [`RangeDownload`] fetches a resource in byte ranges. The first range
+//! tells it the total size (from `Content-Range`) and a validator (`ETag` or
+//! `Last-Modified`); the remaining ranges are sent with `If-Range` so that a
+//! resource that changed mid-download is returned whole instead of being
+//! spliced together from two versions. When the server advertises
+//! `Accept-Ranges: bytes` the ranges are fetched in parallel. Downloaded bytes
+//! are kept after a failure, so calling `start` again resumes the download.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use futures::{StreamExt, TryStreamExt};
+use httpmessenger::AppAction;
+use crate::{HttpClient, HttpError, HttpResponse};
+
+/// Default range size (4 MiB)
+const DEFAULT_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Format a `Range` header value; `end` is inclusive and `None` reads to the end
+pub fn range_header(start: u64, end: Option<u64>) -> String {
+    match end {
+        Some(end) => format!("bytes={}-{}", start, end),
+        None => format!("bytes={}-", start),
+    }
+}
+
+/// Parsed `Content-Range` header of a partial response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentRange {
+    pub start: u64,
+    /// Inclusive end of the range
+    pub end: u64,
+    /// Full size of the resource, if the server knows it
+    pub total: Option<u64>,
+}
+
+impl ContentRange {
+    /// Parse a value such as `bytes 0-99/1000` or `bytes 0-99/*`
+    pub fn parse(value: &str) -> Option<Self> {
+        let range = value.trim().strip_prefix("bytes")?.trim_start();
+        let (range, total) = range.split_once('/')?;
+        let (start, end) = range.split_once('-')?;
+
+        let start = start.trim().parse().ok()?;
+        let end = end.trim().parse().ok()?;
+        let total = match total.trim() {
+            "*" => None,
+            total => Some(total.parse().ok()?),
+        };
+
+        if end < start || total.is_some_and(|total| end >= total) {
+            return None;
+        }
+        Some(Self { start, end, total })
+    }
+
+    /// Number of bytes in the range
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Always false: a satisfiable range covers at least one byte
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+/// Split the bytes from `start` up to `total` into inclusive `(start, end)` ranges
+pub fn split_ranges(start: u64, total: u64, chunk_size: u64) -> Vec<(u64, u64)> {
+    let chunk_size = chunk_size.max(1);
+    (start..total)
+        .step_by(chunk_size as usize)
+        .map(|from| (from, (from + chunk_size).min(total) - 1))
+        .collect()
+}
+
+/// Validator for `If-Range`; weak ETags are not allowed there
+fn response_validator(response: &HttpResponse) -> Option<String> {
+    response
+        .header("ETag")
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| response.header("Last-Modified"))
+        .cloned()
+}
+
+fn accepts_ranges(response: &HttpResponse) -> bool {
+    response
+        .header("Accept-Ranges")
+        .is_some_and(|value| value.split(',').any(|unit| unit.trim().eq_ignore_ascii_case("bytes")))
+}
+
+/// Response to a range request
+enum Chunk {
+    /// `206 Partial Content` with the requested range
+    Partial { range: ContentRange, response: HttpResponse },
+    /// `200 OK`: the server ignored the range or the resource changed
+    Full(Vec<u8>),
+}
+
+/// Bytes downloaded so far, kept across attempts
+#[derive(Debug, Default)]
+struct PartialState {
+    data: Vec<u8>,
+    validator: Option<String>,
+}
+
+/// Download of a resource in byte ranges
+#[derive(Clone)]
+pub struct RangeDownload {
+    client: HttpClient,
+    url: String,
+    chunk_size: u64,
+    parallel: usize,
+    part_retries: u32,
+    retry_delay_ms: u32,
+    state: Rc<RefCell<PartialState>>,
+}
+
+impl RangeDownload {
+    /// Prepare a ranged download of `url`
+    pub fn new(client: &HttpClient, url: &str) -> Self {
+        Self {
+            client: client.clone(),
+            url: url.to_string(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            parallel: 1,
+            part_retries: 3,
+            retry_delay_ms: 1000,
+            state: Rc::new(RefCell::new(PartialState::default())),
+        }
+    }
+
+    /// Set the size of each range in bytes
+    pub fn chunk_size(mut self, bytes: u64) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Fetch up to `ranges` ranges at the same time when the server accepts ranges
+    pub fn parallel(mut self, ranges: usize) -> Self {
+        self.parallel = ranges.max(1);
+        self
+    }
+
+    /// Set how often a failed range is retried and the delay before the first retry
+    ///
+    /// The delay doubles after each failed attempt of the same range.
+    pub fn part_retry(mut self, count: u32, delay_ms: u32) -> Self {
+        self.part_retries = count;
+        self.retry_delay_ms = delay_ms;
+        self
+    }
+
+    /// Continue a download from previously saved bytes and their validator
+    pub fn resume_from(self, data: Vec<u8>, validator: &str) -> Self {
+        *self.state.borrow_mut() = PartialState {
+            data,
+            validator: Some(validator.to_string()),
+        };
+        self
+    }
+
+    /// Bytes downloaded so far and their validator, e.g. to persist after a failure
+    pub fn partial(&self) -> (Vec<u8>, Option<String>) {
+        let state = self.state.borrow();
+        (state.data.clone(), state.validator.clone())
+    }
+
+    /// Download the resource, resuming from any bytes already downloaded
+    pub async fn start(&self) -> Result<Vec<u8>, HttpError> {
+        if let Some(ref dispatch) = self.client.dispatch {
+            dispatch.emit(AppAction::EnableLoader);
+        }
+
+        let result = match self.run().await {
+            // The saved range no longer exists: start over
+            Err(HttpError::Http { status: 416, .. }) if !self.state.borrow().data.is_empty() => {
+                *self.state.borrow_mut() = PartialState::default();
+                self.run().await
+            }
+            result => result,
+        };
+
+        if let Some(ref dispatch) = self.client.dispatch {
+            dispatch.emit(AppAction::DisableLoader);
+        }
+
+        result
+    }
+
+    async fn run(&self) -> Result<Vec<u8>, HttpError> {
+        let offset = self.state.borrow().data.len() as u64;
+        let validator = self.state.borrow().validator.clone();
+
+        // The first range reveals the total size and whether ranges are supported
+        let first = self
+            .fetch_with_retries(offset, offset + self.chunk_size - 1, validator.as_deref())
+            .await?;
+        let (range, response) = match first {
+            Chunk::Partial { range, response } => (range, response),
+            Chunk::Full(data) => return Ok(self.finish(data)),
+        };
+
+        let total = range.total.ok_or(HttpError::InvalidResponse)?;
+        let validator = validator.or_else(|| response_validator(&response));
+        let parallel = if accepts_ranges(&response) { self.parallel } else { 1 };
+        {
+            let mut state = self.state.borrow_mut();
+            state.data.extend_from_slice(&response.bytes);
+            state.validator = validator.clone();
+        }
+        self.report_progress(total);
+
+        let ranges = split_ranges(range.end + 1, total, self.chunk_size);
+        let mut chunks = futures::stream::iter(ranges)
+            .map(|(start, end)| self.fetch_with_retries(start, end, validator.as_deref()))
+            .buffered(parallel);
+
+        // `buffered` yields in order, so the downloaded bytes stay contiguous
+        while let Some(chunk) = chunks.try_next().await? {
+            match chunk {
+                Chunk::Partial { range, response } => {
+                    if range.total != Some(total) {
+                        return Err(HttpError::InvalidResponse);
+                    }
+                    self.state.borrow_mut().data.extend_from_slice(&response.bytes);
+                    self.report_progress(total);
+                }
+                Chunk::Full(data) => return Ok(self.finish(data)),
+            }
+        }
+
+        if self.state.borrow().data.len() as u64 != total {
+            return Err(HttpError::InvalidResponse);
+        }
+
+        let data = std::mem::take(&mut self.state.borrow_mut().data);
+        Ok(self.finish(data))
+    }
+
+    async fn fetch_with_retries(&self, start: u64, end: u64, validator: Option<&str>) -> Result<Chunk, HttpError> {
+        let mut attempt = 0;
+        let mut delay = self.retry_delay_ms;
+
+        loop {
+            match self.fetch(start, end, validator).await {
+                Ok(chunk) => return Ok(chunk),
+                Err(error) if attempt < self.part_retries && is_retryable(&error) => {
+                    attempt += 1;
+                    if delay > 0 {
+                        gloo_timers::future::TimeoutFuture::new(delay).await;
+                    }
+                    delay = delay.saturating_mul(2);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Fetch one range and check that the server returned what was asked for
+    async fn fetch(&self, start: u64, end: u64, validator: Option<&str>) -> Result<Chunk, HttpError> {
+        let mut request = self.client.get(&self.url).range(start, Some(end)).retry(0, 0);
+        if let Some(validator) = validator {
+            request = request.if_range(validator);
+        }
+
+        let response = request.send().await?;
+        if response.status != 206 {
+            return Ok(Chunk::Full(response.bytes));
+        }
+
+        let range = response
+            .header("Content-Range")
+            .and_then(|value| ContentRange::parse(value))
+            .ok_or(HttpError::InvalidResponse)?;
+        if range.start != start || range.len() != response.bytes.len() as u64 {
+            return Err(HttpError::InvalidResponse);
+        }
+
+        Ok(Chunk::Partial { range, response })
+    }
+
+    fn finish(&self, data: Vec<u8>) -> Vec<u8> {
+        *self.state.borrow_mut() = PartialState::default();
+        data
+    }
+
+    fn report_progress(&self, total: u64) {
+        if let Some(ref dispatch) = self.client.dispatch {
+            let downloaded = self.state.borrow().data.len() as f64;
+            dispatch.emit(AppAction::UpdateProgress(downloaded / total.max(1) as f64));
+        }
+    }
+}
+
+impl HttpClient {
+    /// Prepare a ranged, resumable download of `url`
+    pub fn download(&self, url: &str) -> RangeDownload {
+        RangeDownload::new(self, url)
+    }
+}
+
+/// Whether a failed range request should be retried
+fn is_retryable(error: &HttpError) -> bool {
+    match error {
+        HttpError::Network { .. } | HttpError::Timeout => true,
+        HttpError::Http { status, .. } => *status >= 500 || matches!(*status, 408 | 429),
+        _ => false,
+    }
+}
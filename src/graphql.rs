@@ -0,0 +1,274 @@
+//! GraphQL client built on [`HttpClient`]
+//!
+//! Operations are posted as `{query, variables, operationName}` and the
+//! `{data, errors}` envelope is decoded: `data` into a typed value and
+//! `errors` into [`HttpError::GraphQL`]. Automatic persisted queries (APQ)
+//! send only the SHA-256 hash of the query and fall back to the full query
+//! when the server does not know it yet.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use crate::{HttpClient, HttpError, RequestBuilder};
+
+/// Error message (or `extensions.code`) returned for an unknown persisted query
+const PERSISTED_QUERY_NOT_FOUND: &str = "PersistedQueryNotFound";
+
+/// Position of an error in the query document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphQLLocation {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Segment of the response path an error refers to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GraphQLPathSegment {
+    Field(String),
+    Index(usize),
+}
+
+/// An entry of the `errors` array of a GraphQL response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQLError {
+    pub message: String,
+    #[serde(default)]
+    pub locations: Vec<GraphQLLocation>,
+    #[serde(default)]
+    pub path: Vec<GraphQLPathSegment>,
+    #[serde(default)]
+    pub extensions: Option<Value>,
+}
+
+impl GraphQLError {
+    /// `extensions.code`, if the server sent one
+    pub fn code(&self) -> Option<&str> {
+        self.extensions.as_ref()?.get("code")?.as_str()
+    }
+
+    fn is_persisted_query_not_found(&self) -> bool {
+        self.message == PERSISTED_QUERY_NOT_FOUND || self.code() == Some("PERSISTED_QUERY_NOT_FOUND")
+    }
+}
+
+/// Join error messages for [`HttpError::GraphQL`]'s display
+pub(crate) fn describe_errors(errors: &[GraphQLError]) -> String {
+    let messages: Vec<String> = errors
+        .iter()
+        .map(|error| {
+            let path: Vec<String> = error.path
+                .iter()
+                .map(|segment| match segment {
+                    GraphQLPathSegment::Field(field) => field.clone(),
+                    GraphQLPathSegment::Index(index) => index.to_string(),
+                })
+                .collect();
+            if path.is_empty() {
+                error.message.clone()
+            } else {
+                format!("{} (at {})", error.message, path.join("."))
+            }
+        })
+        .collect();
+    messages.join("; ")
+}
+
+/// `{data, errors}` response envelope
+#[derive(Debug, Deserialize)]
+struct GraphQLResponse {
+    #[serde(default)]
+    data: Option<Value>,
+    #[serde(default)]
+    errors: Vec<GraphQLError>,
+}
+
+/// SHA-256 hash of a query as lowercase hex, as used by persisted queries
+pub fn query_hash(query: &str) -> String {
+    Sha256::digest(query.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Name of the first operation in a query document, if it is named
+pub fn operation_name(query: &str) -> Option<String> {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut depth = 0usize;
+    let mut expect_name = false;
+    let mut chars = query.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '#' => {
+                while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+            }
+            '{' | '(' if expect_name => return None,
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            c if is_name_char(c) => {
+                let mut end = start + c.len_utf8();
+                while let Some((index, c)) = chars.next_if(|&(_, c)| is_name_char(c)) {
+                    end = index + c.len_utf8();
+                }
+                let word = &query[start..end];
+
+                if expect_name {
+                    return Some(word.to_string());
+                }
+                expect_name = depth == 0 && matches!(word, "query" | "mutation" | "subscription");
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Decode a GraphQL response body into `data`, mapping `errors` to [`HttpError::GraphQL`]
+pub fn decode_response<T: DeserializeOwned>(body: &str) -> Result<T, HttpError> {
+    let response: GraphQLResponse = serde_json::from_str(body).map_err(|e| HttpError::Serialization {
+        message: format!("Failed to deserialize GraphQL response: {}", e),
+    })?;
+
+    if !response.errors.is_empty() {
+        return Err(HttpError::GraphQL {
+            errors: response.errors,
+            data: response.data.filter(|data| !data.is_null()),
+        });
+    }
+
+    let data = response.data.unwrap_or(Value::Null);
+    serde_json::from_value(data).map_err(|e| HttpError::Serialization {
+        message: format!("Failed to deserialize GraphQL data: {}", e),
+    })
+}
+
+/// Builder for a single GraphQL operation
+pub struct GraphQLBuilder {
+    request: RequestBuilder,
+    query: String,
+    variables: Option<Value>,
+    operation_name: Option<String>,
+    persisted: bool,
+}
+
+impl GraphQLBuilder {
+    fn new(request: RequestBuilder, query: &str) -> Self {
+        Self {
+            request,
+            query: query.to_string(),
+            variables: None,
+            operation_name: operation_name(query),
+            persisted: false,
+        }
+    }
+
+    /// Set the operation's variables
+    pub fn variables<V: Serialize>(mut self, variables: &V) -> Result<Self, HttpError> {
+        let variables = serde_json::to_value(variables).map_err(|e| HttpError::Serialization {
+            message: format!("Failed to serialize GraphQL variables: {}", e),
+        })?;
+        self.variables = Some(variables);
+        Ok(self)
+    }
+
+    /// Select the operation to run; defaults to the first named operation in the query
+    pub fn operation_name(mut self, name: &str) -> Self {
+        self.operation_name = Some(name.to_string());
+        self
+    }
+
+    /// Send the query's hash first and the full query only if the server asks for it
+    pub fn persisted_query(mut self, enabled: bool) -> Self {
+        self.persisted = enabled;
+        self
+    }
+
+    /// Add a header to the request
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.request = self.request.header(name, value);
+        self
+    }
+
+    /// Enable automatic loader state management
+    pub fn with_loader(mut self, enabled: bool) -> Self {
+        self.request = self.request.with_loader(enabled);
+        self
+    }
+
+    /// Enable notifications
+    pub fn with_notifications(mut self, enabled: bool) -> Self {
+        self.request = self.request.with_notifications(enabled);
+        self
+    }
+
+    /// Request body for this operation
+    pub fn body(&self, include_query: bool) -> Value {
+        let mut body = json!({});
+        if include_query || !self.persisted {
+            body["query"] = json!(self.query);
+        }
+        if let Some(ref variables) = self.variables {
+            body["variables"] = variables.clone();
+        }
+        if let Some(ref name) = self.operation_name {
+            body["operationName"] = json!(name);
+        }
+        if self.persisted {
+            body["extensions"] = json!({
+                "persistedQuery": { "version": 1, "sha256Hash": query_hash(&self.query) }
+            });
+        }
+        body
+    }
+
+    /// Send the operation and decode `data` into `T`
+    ///
+    /// Notifications and the error handler see the final outcome once, after
+    /// GraphQL errors have been decoded and a persisted query miss retried.
+    pub async fn send<T: DeserializeOwned>(self) -> Result<T, HttpError> {
+        let result = self.send_operation().await;
+        self.request.report_outcome(&result);
+        result
+    }
+
+    async fn send_operation<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        if self.persisted {
+            match self.send_body(false).await {
+                Err(HttpError::GraphQL { ref errors, .. })
+                    if errors.iter().any(GraphQLError::is_persisted_query_not_found) => {}
+                result => return result,
+            }
+        }
+        self.send_body(true).await
+    }
+
+    async fn send_body<T: DeserializeOwned>(&self, include_query: bool) -> Result<T, HttpError> {
+        let mut request = self.request
+            .clone()
+            .with_notifications(false)
+            .without_error_handler()
+            .json(&self.body(include_query))?;
+        if let Some(ref name) = self.operation_name {
+            request = request.call_name(name);
+        }
+
+        match request.send().await {
+            Ok(response) => decode_response(response.text()),
+            // Servers often report GraphQL errors with a 4xx/5xx status
+            Err(HttpError::Http { status, message, body: Some(body) }) => match decode_response::<Value>(&body) {
+                Err(error @ HttpError::GraphQL { .. }) => Err(error),
+                _ => Err(HttpError::Http { status, message, body: Some(body) }),
+            },
+            Err(error) => Err(error),
+        }
+    }
+}
+
+impl HttpClient {
+    /// Prepare a GraphQL operation against the endpoint at `path`
+    pub fn graphql(&self, path: &str, query: &str) -> GraphQLBuilder {
+        GraphQLBuilder::new(self.post(path), query)
+    }
+}
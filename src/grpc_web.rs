@@ -0,0 +1,422 @@
+//! gRPC-Web calls built on [`HttpClient`]
+//!
+//! Messages are prost-encoded and wrapped in gRPC-Web frames (a flag byte and
+//! a big-endian length prefix). The response carries data frames followed by
+//! a trailer frame holding `grpc-status` / `grpc-message`; a non-zero status
+//! becomes [`HttpError::Grpc`]. Both the binary framing and the base64 text
+//! framing used by proxies without binary body support are handled.
+
+use std::collections::{HashMap, VecDeque};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures::stream::{self, LocalBoxStream, StreamExt};
+use percent_encoding::percent_decode_str;
+use prost::Message;
+use crate::body_stream::BodyReader;
+use crate::scheduler::SchedulerPermit;
+use crate::{HttpClient, HttpError, RequestBuilder};
+
+/// Flag bit marking a trailer frame
+const TRAILER_FLAG: u8 = 0x80;
+
+/// Flag bit marking a compressed frame
+const COMPRESSED_FLAG: u8 = 0x01;
+
+/// Length of the flag byte plus the length prefix
+const FRAME_HEADER_LEN: usize = 5;
+
+/// gRPC-Web wire format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrpcWebMode {
+    /// `application/grpc-web+proto`, frames sent as raw bytes
+    #[default]
+    Binary,
+    /// `application/grpc-web-text+proto`, frames sent base64 encoded
+    Text,
+}
+
+impl GrpcWebMode {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            GrpcWebMode::Binary => "application/grpc-web+proto",
+            GrpcWebMode::Text => "application/grpc-web-text+proto",
+        }
+    }
+}
+
+/// A decoded gRPC-Web frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrpcFrame {
+    /// Encoded message
+    Data(Vec<u8>),
+    /// Trailer metadata with lowercased keys
+    Trailers(HashMap<String, String>),
+}
+
+/// Wrap an encoded message in a data frame
+pub fn encode_frame(message: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + message.len());
+    frame.push(0);
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(message);
+    frame
+}
+
+/// Parse the `name: value` lines of a trailer frame
+pub fn parse_trailers(payload: &[u8]) -> HashMap<String, String> {
+    String::from_utf8_lossy(payload)
+        .split("\r\n")
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Status carried by `grpc-status` / `grpc-message`, if present
+///
+/// Status 0 is `Ok(())`; anything else maps to [`HttpError::Grpc`] with the
+/// percent-decoded message.
+pub fn grpc_status(metadata: &HashMap<String, String>) -> Option<Result<(), HttpError>> {
+    let get = |name: &str| {
+        metadata
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+
+    let code = match get("grpc-status")?.trim().parse::<u32>() {
+        Ok(0) => return Some(Ok(())),
+        Ok(code) => code,
+        Err(_) => return Some(Err(HttpError::InvalidResponse)),
+    };
+    let message = get("grpc-message")
+        .map(|message| percent_decode_str(message).decode_utf8_lossy().into_owned())
+        .unwrap_or_default();
+
+    Some(Err(HttpError::Grpc { code, message }))
+}
+
+/// Incremental decoder for gRPC-Web response bodies
+#[derive(Debug, Default)]
+pub struct GrpcWebDecoder {
+    mode: GrpcWebMode,
+    text: Vec<u8>,
+    buffer: Vec<u8>,
+}
+
+impl GrpcWebDecoder {
+    pub fn new(mode: GrpcWebMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Feed a chunk of the body, returning the frames it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<GrpcFrame>, HttpError> {
+        match self.mode {
+            GrpcWebMode::Binary => self.buffer.extend_from_slice(chunk),
+            GrpcWebMode::Text => self.decode_text(chunk)?,
+        }
+
+        let mut frames = Vec::new();
+        while self.buffer.len() >= FRAME_HEADER_LEN {
+            let flags = self.buffer[0];
+            let len = u32::from_be_bytes([self.buffer[1], self.buffer[2], self.buffer[3], self.buffer[4]]) as usize;
+            if self.buffer.len() < FRAME_HEADER_LEN + len {
+                break;
+            }
+
+            if flags & COMPRESSED_FLAG != 0 {
+                return Err(HttpError::Serialization {
+                    message: "Compressed gRPC-Web frames are not supported".to_string(),
+                });
+            }
+
+            let payload: Vec<u8> = self.buffer.drain(..FRAME_HEADER_LEN + len).skip(FRAME_HEADER_LEN).collect();
+            frames.push(if flags & TRAILER_FLAG != 0 {
+                GrpcFrame::Trailers(parse_trailers(&payload))
+            } else {
+                GrpcFrame::Data(payload)
+            });
+        }
+        Ok(frames)
+    }
+
+    /// Check that the body did not end in the middle of a frame
+    pub fn finish(&self) -> Result<(), HttpError> {
+        if self.buffer.is_empty() && self.text.is_empty() {
+            Ok(())
+        } else {
+            Err(HttpError::InvalidResponse)
+        }
+    }
+
+    /// Decode complete base64 quanta; each response chunk may carry its own padding
+    fn decode_text(&mut self, chunk: &[u8]) -> Result<(), HttpError> {
+        self.text.extend(chunk.iter().filter(|b| !b.is_ascii_whitespace()));
+
+        let complete = self.text.len() - self.text.len() % 4;
+        for quantum in self.text[..complete].chunks(4) {
+            let bytes = STANDARD.decode(quantum).map_err(|e| HttpError::Serialization {
+                message: format!("Invalid gRPC-Web text body: {}", e),
+            })?;
+            self.buffer.extend(bytes);
+        }
+        self.text.drain(..complete);
+        Ok(())
+    }
+}
+
+fn decode_message<R: Message + Default>(payload: &[u8]) -> Result<R, HttpError> {
+    R::decode(payload).map_err(|e| HttpError::Serialization {
+        message: format!("Failed to decode gRPC message: {}", e),
+    })
+}
+
+/// Decode a complete unary response from its headers and body
+///
+/// A trailers-only response carries its status in the headers; otherwise the
+/// status comes from the trailer frame after the message.
+pub fn decode_unary<R: Message + Default>(
+    mode: GrpcWebMode,
+    headers: &HashMap<String, String>,
+    body: &[u8],
+) -> Result<R, HttpError> {
+    let header_status = grpc_status(headers);
+    if let Some(Err(error)) = header_status {
+        return Err(error);
+    }
+
+    let mut decoder = GrpcWebDecoder::new(mode);
+    let frames = decoder.feed(body)?;
+    decoder.finish()?;
+
+    let mut message = None;
+    let mut status = header_status;
+    for frame in frames {
+        match frame {
+            GrpcFrame::Data(payload) => {
+                message.get_or_insert(payload);
+            }
+            GrpcFrame::Trailers(trailers) => status = grpc_status(&trailers),
+        }
+    }
+
+    status.ok_or(HttpError::InvalidResponse)??;
+    decode_message(&message.ok_or(HttpError::InvalidResponse)?)
+}
+
+/// Decoding state carried between server stream items
+struct StreamState<R> {
+    reader: Option<BodyReader>,
+    decoder: GrpcWebDecoder,
+    pending: VecDeque<Result<R, HttpError>>,
+    header_status: bool,
+    /// Taken when the call's outcome is reported, so it happens once
+    request: Option<RequestBuilder>,
+    /// Scheduler slot, held until the body has been read
+    _permit: Option<SchedulerPermit>,
+}
+
+impl<R: Message + Default> StreamState<R> {
+    fn push_frames(&mut self, frames: Result<Vec<GrpcFrame>, HttpError>) {
+        let frames = match frames {
+            Ok(frames) => frames,
+            Err(error) => return self.fail(error),
+        };
+
+        for frame in frames {
+            match frame {
+                GrpcFrame::Data(payload) => self.pending.push_back(decode_message(&payload)),
+                GrpcFrame::Trailers(trailers) => {
+                    let status = grpc_status(&trailers).unwrap_or(Ok(()));
+                    if let Err(ref error) = status {
+                        self.pending.push_back(Err(error.clone()));
+                    }
+                    self.reader = None;
+                    self.report(&status);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn fail(&mut self, error: HttpError) {
+        self.reader = None;
+        self.report(&Err(error.clone()));
+        self.pending.push_back(Err(error));
+    }
+
+    /// Report the final status of the call once it is known
+    fn report(&mut self, result: &Result<(), HttpError>) {
+        if let Some(request) = self.request.take() {
+            request.report_outcome(result);
+        }
+    }
+}
+
+/// Builder for a gRPC-Web call
+pub struct GrpcWebBuilder {
+    request: RequestBuilder,
+    mode: GrpcWebMode,
+    message: Vec<u8>,
+}
+
+impl GrpcWebBuilder {
+    fn new(request: RequestBuilder) -> Self {
+        Self {
+            request,
+            mode: GrpcWebMode::Binary,
+            message: Vec::new(),
+        }
+    }
+
+    /// Set the request message
+    pub fn message<M: Message>(mut self, message: &M) -> Self {
+        self.message = message.encode_to_vec();
+        self
+    }
+
+    /// Select the binary or text framing; defaults to binary
+    pub fn mode(mut self, mode: GrpcWebMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Add request metadata
+    pub fn metadata(mut self, name: &str, value: &str) -> Self {
+        self.request = self.request.header(name, value);
+        self
+    }
+
+    /// Set the call deadline, sent as `grpc-timeout` and applied to the request
+    pub fn deadline(mut self, ms: u32) -> Self {
+        self.request = self.request.header("grpc-timeout", &format!("{}m", ms)).timeout(ms);
+        self
+    }
+
+    /// Enable automatic loader state management
+    pub fn with_loader(mut self, enabled: bool) -> Self {
+        self.request = self.request.with_loader(enabled);
+        self
+    }
+
+    /// Enable notifications
+    pub fn with_notifications(mut self, enabled: bool) -> Self {
+        self.request = self.request.with_notifications(enabled);
+        self
+    }
+
+    /// Set the call name used for loader tracking
+    pub fn call_name(mut self, name: &str) -> Self {
+        self.request = self.request.call_name(name);
+        self
+    }
+
+    /// Request body holding the framed message
+    pub fn body(&self) -> Vec<u8> {
+        let frame = encode_frame(&self.message);
+        match self.mode {
+            GrpcWebMode::Binary => frame,
+            GrpcWebMode::Text => STANDARD.encode(frame).into_bytes(),
+        }
+    }
+
+    /// The underlying request; notifications and error reporting happen once
+    /// the gRPC status is known
+    fn prepared_request(&self) -> RequestBuilder {
+        self.request
+            .clone()
+            .header("Content-Type", self.mode.content_type())
+            .header("Accept", self.mode.content_type())
+            .header("X-Grpc-Web", "1")
+            .binary(self.body())
+            .with_notifications(false)
+            .without_error_handler()
+    }
+
+    /// Make a unary call and decode the response message
+    pub async fn unary<R: Message + Default>(self) -> Result<R, HttpError> {
+        let result = match self.prepared_request().send().await {
+            Ok(response) => decode_unary(self.mode, &response.headers, &response.bytes),
+            Err(error) => Err(error),
+        };
+        self.request.report_outcome(&result);
+        result
+    }
+
+    /// Make a server-streaming call, yielding each response message as it arrives
+    ///
+    /// Fails if the call cannot be started or ends with a non-zero status in
+    /// the headers; a non-zero status in the trailers ends the stream with an
+    /// [`HttpError::Grpc`] item. Notifications and the error handler fire once
+    /// the trailers or the end of the body have been read.
+    ///
+    /// The call goes through the client's rate limiter, circuit breaker and
+    /// scheduler; its slot is held until the stream ends or is dropped. The
+    /// loader covers opening the stream.
+    pub async fn server_streaming<R>(self) -> Result<LocalBoxStream<'static, Result<R, HttpError>>, HttpError>
+    where
+        R: Message + Default + 'static,
+    {
+        let opened = self.prepared_request().execute_stream().await.and_then(|(response, permit)| {
+            let headers: HashMap<String, String> = response.headers().entries().collect();
+            let header_status = grpc_status(&headers).transpose()?.is_some();
+            Ok((BodyReader::new(&response)?, header_status, permit))
+        });
+        let (reader, header_status, permit) = match opened {
+            Ok(opened) => opened,
+            Err(error) => {
+                self.request.report_outcome::<()>(&Err(error.clone()));
+                return Err(error);
+            }
+        };
+
+        let state = StreamState::<R> {
+            reader: Some(reader),
+            decoder: GrpcWebDecoder::new(self.mode),
+            pending: VecDeque::new(),
+            header_status,
+            request: Some(self.request),
+            _permit: permit,
+        };
+
+        let items = stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(item) = state.pending.pop_front() {
+                    return Some((item, state));
+                }
+
+                let reader = state.reader.as_ref()?;
+                match reader.next_chunk().await {
+                    Ok(Some(chunk)) => {
+                        let frames = state.decoder.feed(&chunk);
+                        state.push_frames(frames);
+                    }
+                    // The body ended without a trailer frame
+                    Ok(None) if state.header_status => {
+                        state.reader = None;
+                        let result = state.decoder.finish();
+                        if let Err(ref error) = result {
+                            state.pending.push_back(Err(error.clone()));
+                        }
+                        state.report(&result);
+                    }
+                    Ok(None) => state.fail(HttpError::InvalidResponse),
+                    Err(error) => state.fail(error),
+                }
+            }
+        });
+
+        Ok(items.boxed_local())
+    }
+}
+
+impl HttpClient {
+    /// Prepare a gRPC-Web call to `path` (`/package.Service/Method`)
+    pub fn grpc_web(&self, path: &str) -> GrpcWebBuilder {
+        GrpcWebBuilder::new(self.post(path))
+    }
+}
@@ -0,0 +1,316 @@
+//! JSON-RPC 2.0 client built on [`HttpClient`]
+//!
+//! Calls get increasing numeric ids, results are decoded into typed values and
+//! error objects become [`HttpError::JsonRpc`]. A [`JsonRpcBatch`] collects
+//! several calls, sends them in one HTTP request and resolves each call's
+//! future independently as the matching response is found.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use futures::channel::oneshot;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use crate::{HttpClient, HttpError, RequestBuilder};
+
+/// Build a request object; notifications have no id
+pub fn request_object(id: Option<u64>, method: &str, params: Value) -> Value {
+    let mut request = json!({ "jsonrpc": "2.0", "method": method });
+    if !params.is_null() {
+        request["params"] = params;
+    }
+    if let Some(id) = id {
+        request["id"] = json!(id);
+    }
+    request
+}
+
+/// Extract the result of a response object, mapping an error object to [`HttpError::JsonRpc`]
+pub fn decode_response_object(response: &Value) -> Result<Value, HttpError> {
+    if let Some(error) = response.get("error").filter(|error| !error.is_null()) {
+        return Err(HttpError::JsonRpc {
+            code: error.get("code").and_then(Value::as_i64).unwrap_or_default(),
+            message: error.get("message").and_then(Value::as_str).unwrap_or_default().to_string(),
+            data: error.get("data").cloned(),
+        });
+    }
+
+    response.get("result").cloned().ok_or(HttpError::InvalidResponse)
+}
+
+/// Match the responses of a batch to call ids
+///
+/// Calls without a matching response get [`HttpError::InvalidResponse`]. A
+/// single error object instead of an array (e.g. a parse error for the whole
+/// batch) is reported to every call.
+pub fn match_batch_responses(ids: &[u64], responses: &Value) -> HashMap<u64, Result<Value, HttpError>> {
+    let mut results = HashMap::new();
+
+    match responses {
+        Value::Array(responses) => {
+            for response in responses {
+                if let Some(id) = response.get("id").and_then(Value::as_u64) {
+                    results.insert(id, decode_response_object(response));
+                }
+            }
+        }
+        response => {
+            let error = decode_response_object(response).err().unwrap_or(HttpError::InvalidResponse);
+            for &id in ids {
+                results.insert(id, Err(error.clone()));
+            }
+        }
+    }
+
+    for &id in ids {
+        results.entry(id).or_insert(Err(HttpError::InvalidResponse));
+    }
+    results
+}
+
+/// Parse a non-2xx body that carries JSON-RPC responses
+///
+/// Servers often answer failed calls with a 4xx/5xx status and an error
+/// object in the body; batches get their response array. Other bodies are
+/// not JSON-RPC responses and yield `None`.
+pub fn error_status_body(body: &str) -> Option<Value> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    let is_response = match value {
+        Value::Array(_) => true,
+        Value::Object(ref object) => object.get("error").is_some_and(|error| !error.is_null()),
+        _ => false,
+    };
+    is_response.then_some(value)
+}
+
+fn to_params<P: Serialize>(params: &P) -> Result<Value, HttpError> {
+    serde_json::to_value(params).map_err(|e| HttpError::Serialization {
+        message: format!("Failed to serialize JSON-RPC params: {}", e),
+    })
+}
+
+fn from_result<R: DeserializeOwned>(result: Value) -> Result<R, HttpError> {
+    serde_json::from_value(result).map_err(|e| HttpError::Serialization {
+        message: format!("Failed to deserialize JSON-RPC result: {}", e),
+    })
+}
+
+/// JSON-RPC client for one endpoint; clones share the id counter
+#[derive(Clone)]
+pub struct JsonRpcClient {
+    client: HttpClient,
+    path: String,
+    next_id: Rc<Cell<u64>>,
+}
+
+impl JsonRpcClient {
+    /// Create a client posting to `path`
+    pub fn new(client: &HttpClient, path: &str) -> Self {
+        Self {
+            client: client.clone(),
+            path: path.to_string(),
+            next_id: Rc::new(Cell::new(1)),
+        }
+    }
+
+    fn next_id(&self) -> u64 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    /// Request used for notifications and error reporting of one call or batch
+    fn request(&self, call_name: &str) -> RequestBuilder {
+        self.client.post(&self.path).call_name(call_name)
+    }
+
+    /// Post `body`; the caller reports the outcome once it is decoded
+    async fn post(&self, request: &RequestBuilder, body: &Value) -> Result<Option<Value>, HttpError> {
+        let result = request
+            .clone()
+            .with_notifications(false)
+            .without_error_handler()
+            .json(body)?
+            .send()
+            .await;
+
+        match result {
+            // Notifications and notification-only batches get an empty response
+            Ok(response) if response.text().trim().is_empty() => Ok(None),
+            Ok(response) => response.json().map(Some),
+            Err(HttpError::Http { status, message, body: Some(body) }) => match error_status_body(&body) {
+                Some(responses) => Ok(Some(responses)),
+                None => Err(HttpError::Http { status, message, body: Some(body) }),
+            },
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Call `method` and decode its result
+    pub async fn call<P, R>(&self, method: &str, params: &P) -> Result<R, HttpError>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let body = request_object(Some(self.next_id()), method, to_params(params)?);
+        let request = self.request(method);
+        let result = match self.post(&request, &body).await {
+            Ok(Some(response)) => decode_response_object(&response).and_then(from_result),
+            Ok(None) => Err(HttpError::InvalidResponse),
+            Err(error) => Err(error),
+        };
+        request.report_outcome(&result);
+        result
+    }
+
+    /// Send a notification, which has no response
+    pub async fn notify<P: Serialize>(&self, method: &str, params: &P) -> Result<(), HttpError> {
+        let body = request_object(None, method, to_params(params)?);
+        let request = self.request(method);
+        let result = self.post(&request, &body).await.map(|_| ());
+        request.report_outcome(&result);
+        result
+    }
+
+    /// Start a batch of calls sent in a single HTTP request
+    pub fn batch(&self) -> JsonRpcBatch {
+        JsonRpcBatch {
+            rpc: self.clone(),
+            calls: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+/// A call waiting in a batch
+struct PendingCall {
+    id: Option<u64>,
+    request: Value,
+    sender: Option<oneshot::Sender<Result<Value, HttpError>>>,
+}
+
+/// Calls collected to be sent in one request
+pub struct JsonRpcBatch {
+    rpc: JsonRpcClient,
+    calls: RefCell<Vec<PendingCall>>,
+}
+
+impl JsonRpcBatch {
+    /// Add a call; the returned future resolves once the batch has been sent
+    pub fn call<P, R>(&self, method: &str, params: &P) -> Result<BatchCall<R>, HttpError>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let id = self.rpc.next_id();
+        let (sender, receiver) = oneshot::channel();
+        self.calls.borrow_mut().push(PendingCall {
+            id: Some(id),
+            request: request_object(Some(id), method, to_params(params)?),
+            sender: Some(sender),
+        });
+
+        Ok(BatchCall {
+            receiver,
+            _result: PhantomData,
+        })
+    }
+
+    /// Add a notification
+    pub fn notify<P: Serialize>(&self, method: &str, params: &P) -> Result<(), HttpError> {
+        self.calls.borrow_mut().push(PendingCall {
+            id: None,
+            request: request_object(None, method, to_params(params)?),
+            sender: None,
+        });
+        Ok(())
+    }
+
+    /// Number of calls and notifications in the batch
+    pub fn len(&self) -> usize {
+        self.calls.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.borrow().is_empty()
+    }
+
+    /// Send the batch and resolve every call's future
+    ///
+    /// Returns an error if the request itself failed or the server rejected
+    /// the whole batch; that error is also delivered to every call and is the
+    /// one reported to the error handler. Errors of single calls are left to
+    /// their futures.
+    pub async fn send(self) -> Result<(), HttpError> {
+        let calls = self.calls.into_inner();
+        if calls.is_empty() {
+            return Ok(());
+        }
+
+        let body = Value::Array(calls.iter().map(|call| call.request.clone()).collect());
+        let ids: Vec<u64> = calls.iter().filter_map(|call| call.id).collect();
+
+        let request = self.rpc.request("jsonrpc_batch");
+        let responses = match self.rpc.post(&request, &body).await {
+            // A single error object rejects the whole batch
+            Ok(Some(response)) if !response.is_array() && !ids.is_empty() => {
+                Err(decode_response_object(&response).err().unwrap_or(HttpError::InvalidResponse))
+            }
+            result => result,
+        };
+        request.report_outcome(&responses);
+
+        let responses = match responses {
+            Ok(responses) => responses,
+            Err(error) => {
+                for sender in calls.into_iter().filter_map(|call| call.sender) {
+                    let _ = sender.send(Err(error.clone()));
+                }
+                return Err(error);
+            }
+        };
+
+        let mut results = match responses {
+            Some(responses) => match_batch_responses(&ids, &responses),
+            None => ids.iter().map(|&id| (id, Err(HttpError::InvalidResponse))).collect(),
+        };
+
+        for call in calls {
+            if let (Some(id), Some(sender)) = (call.id, call.sender) {
+                let result = results.remove(&id).unwrap_or(Err(HttpError::InvalidResponse));
+                let _ = sender.send(result);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Future of a single call in a batch
+pub struct BatchCall<R> {
+    receiver: oneshot::Receiver<Result<Value, HttpError>>,
+    _result: PhantomData<fn() -> R>,
+}
+
+impl<R: DeserializeOwned> Future for BatchCall<R> {
+    type Output = Result<R, HttpError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match Pin::new(&mut self.receiver).poll(cx) {
+            Poll::Ready(Ok(result)) => Poll::Ready(result.and_then(from_result)),
+            // The batch was dropped without being sent
+            Poll::Ready(Err(oneshot::Canceled)) => Poll::Ready(Err(HttpError::Cancelled)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl HttpClient {
+    /// Create a JSON-RPC 2.0 client posting to `path`
+    pub fn json_rpc(&self, path: &str) -> JsonRpcClient {
+        JsonRpcClient::new(self, path)
+    }
+}
@@ -44,7 +44,10 @@
 //!     .await?;
 //! ```
 
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
+use futures::future::{FutureExt, LocalBoxFuture, Shared};
 use serde::{Serialize, de::DeserializeOwned};
 use thiserror::Error;
 use yew::prelude::*;
@@ -52,6 +55,53 @@ use reqwasm::http::{Request, Method};
 use httpmessenger::{AppAction, StoreDispatcher, use_store};
 use gloo_console::log;
 
+pub mod scheduler;
+pub mod rate_limit;
+pub mod circuit_breaker;
+pub mod query;
+pub mod path;
+pub mod resolve;
+pub mod multipart;
+pub mod tus;
+pub mod presigned;
+pub mod download;
+pub mod save;
+pub mod sse;
+pub mod ws;
+pub mod ndjson;
+pub mod graphql;
+pub mod jsonrpc;
+pub mod codec;
+pub mod compression;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+#[cfg(feature = "grpc-web")]
+pub mod grpc_web;
+mod body_stream;
+mod random;
+
+pub use scheduler::{Priority, RequestScheduler};
+pub use rate_limit::{RateLimit, RateLimiter};
+pub use circuit_breaker::{CircuitBreaker, CircuitPermit, CircuitState};
+pub use query::{ArrayFormat, QueryValue};
+pub use multipart::{Multipart, Part};
+pub use tus::{LocalStorageUrlStore, MemoryUrlStore, TusUpload, TusUrlStore};
+pub use presigned::{CompletedPart, PresignedUpload};
+pub use download::{ContentRange, RangeDownload};
+pub use sse::{use_sse, SseConnection, SseEvent, SseParser, SseState, SseStream, SseTransport};
+pub use ws::{WsBuilder, WsClient, WsMessage, WsMessages, WsState};
+pub use ndjson::NdjsonDecoder;
+pub use graphql::{GraphQLBuilder, GraphQLError, GraphQLLocation, GraphQLPathSegment};
+pub use jsonrpc::{BatchCall, JsonRpcBatch, JsonRpcClient};
+pub use codec::{BuiltinCodec, Codec, JsonCodec};
+pub use compression::{CompressionPolicy, Encoding};
+#[cfg(feature = "msgpack")]
+pub use codec::MsgPackCodec;
+#[cfg(feature = "cbor")]
+pub use codec::CborCodec;
+#[cfg(feature = "grpc-web")]
+pub use grpc_web::{GrpcFrame, GrpcWebBuilder, GrpcWebDecoder, GrpcWebMode};
+
 #[cfg(test)]
 pub mod tests;
 
@@ -64,12 +114,15 @@ pub enum HttpError {
     #[error("Request timeout")]
     Timeout,
     
-    #[error("Invalid URL: {url}")]
-    InvalidUrl { url: String },
+    #[error("Invalid URL {url}: {reason}")]
+    InvalidUrl { url: String, reason: String },
     
     #[error("Serialization error: {message}")]
     Serialization { message: String },
     
+    #[error("Invalid JSON on line {line}: {message}")]
+    InvalidLine { line: usize, message: String },
+    
     #[error("HTTP {status}: {message}")]
     Http { status: u16, message: String, body: Option<String> },
     
@@ -81,29 +134,86 @@ pub enum HttpError {
     
     #[error("Configuration error: {message}")]
     Configuration { message: String },
+    
+    #[error("Circuit open for {key}, retry after {retry_after_ms}ms")]
+    CircuitOpen { key: String, retry_after_ms: u32 },
+    
+    #[error("GraphQL error: {}", graphql::describe_errors(.errors))]
+    GraphQL { errors: Vec<graphql::GraphQLError>, data: Option<serde_json::Value> },
+
+    #[error("JSON-RPC error {code}: {message}")]
+    JsonRpc { code: i64, message: String, data: Option<serde_json::Value> },
+
+    #[error("gRPC error {code}: {message}")]
+    Grpc { code: u32, message: String },
 }
 
 /// HTTP response wrapper with additional metadata
+///
+/// Build one with [`HttpResponse::new`], e.g. to stub responses in tests.
 #[derive(Debug, Clone)]
 pub struct HttpResponse {
     pub status: u16,
     pub headers: HashMap<String, String>,
-    pub body: String,
+    /// Raw response body; [`HttpResponse::text`] decodes it on demand
+    pub bytes: Vec<u8>,
     pub url: String,
     pub call_name: Option<String>,
+    /// Which hedged attempt produced the response (0 is the original request)
+    pub hedge_attempt: Option<u32>,
+    /// Unexpanded path template of the request, e.g. `/users/{id}`
+    pub path_template: Option<String>,
+    /// Lossy text of a body that is not valid UTF-8, decoded on first use
+    lossy_text: std::sync::OnceLock<String>,
 }
 
 impl HttpResponse {
+    /// Create a response with the given status, final URL and body
+    pub fn new(status: u16, url: &str, bytes: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers: HashMap::new(),
+            bytes,
+            url: url.to_string(),
+            call_name: None,
+            hedge_attempt: None,
+            path_template: None,
+            lossy_text: std::sync::OnceLock::new(),
+        }
+    }
+    
+    /// Add a response header
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_string(), value.to_string());
+        self
+    }
+    
     /// Parse JSON response body
     pub fn json<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
-        serde_json::from_str(&self.body).map_err(|e| HttpError::Serialization {
+        serde_json::from_slice(&self.bytes).map_err(|e| HttpError::Serialization {
             message: format!("Failed to deserialize JSON: {}", e),
         })
     }
     
     /// Get response body as text
+    ///
+    /// Invalid UTF-8 sequences are replaced with U+FFFD.
     pub fn text(&self) -> &str {
-        &self.body
+        match std::str::from_utf8(&self.bytes) {
+            Ok(text) => text,
+            Err(_) => self.lossy_text.get_or_init(|| String::from_utf8_lossy(&self.bytes).into_owned()),
+        }
+    }
+    
+    /// Get response body as text
+    #[deprecated(since = "0.3.0", note = "use `text()`; the body is stored as `bytes`")]
+    pub fn body(&self) -> &str {
+        self.text()
+    }
+    
+    /// Get the raw response body
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
     }
     
     /// Check if request was successful (2xx status)
@@ -154,6 +264,19 @@ impl HttpMethod {
             HttpMethod::Options => "OPTIONS",
         }
     }
+    
+    /// Whether repeating the method has the same effect as sending it once
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self,
+            HttpMethod::Get | HttpMethod::Head | HttpMethod::Options | HttpMethod::Put | HttpMethod::Delete
+        )
+    }
+    
+    /// Whether the method is safe (read-only) and can be shared between callers
+    pub fn is_safe(&self) -> bool {
+        matches!(self, HttpMethod::Get | HttpMethod::Head | HttpMethod::Options)
+    }
 }
 
 /// Request body types
@@ -162,10 +285,20 @@ pub enum RequestBody {
     None,
     Text(String),
     Json(String),
+    UrlEncoded(String),
     FormData(web_sys::FormData),
     Binary(Vec<u8>),
 }
 
+/// Policy for sending duplicate requests when the first one is slow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HedgePolicy {
+    /// Delay before each additional attempt is fired
+    pub after_ms: u32,
+    /// Maximum number of additional attempts
+    pub max_extra: u32,
+}
+
 /// Request configuration
 #[derive(Debug, Clone)]
 pub struct RequestConfig {
@@ -180,6 +313,15 @@ pub struct RequestConfig {
     pub call_name: Option<String>,
     pub retry_count: u32,
     pub retry_delay_ms: u32,
+    pub deduplicate: bool,
+    pub priority: Priority,
+    pub idempotency_key: Option<String>,
+    pub hedge: Option<HedgePolicy>,
+    pub query: Vec<(String, QueryValue)>,
+    pub array_format: ArrayFormat,
+    pub path_params: HashMap<String, String>,
+    pub allowed_origins: Vec<String>,
+    pub compression: Option<CompressionPolicy>,
 }
 
 impl Default for RequestConfig {
@@ -196,15 +338,66 @@ impl Default for RequestConfig {
             call_name: None,
             retry_count: 0,
             retry_delay_ms: 1000,
+            deduplicate: false,
+            priority: Priority::Normal,
+            idempotency_key: None,
+            hedge: None,
+            query: Vec::new(),
+            array_format: ArrayFormat::Repeat,
+            path_params: HashMap::new(),
+            allowed_origins: Vec::new(),
+            compression: None,
         }
     }
 }
 
+impl RequestConfig {
+    /// URL the request is sent to, with path parameters substituted and
+    /// query parameters encoded
+    pub fn request_url(&self) -> Result<String, HttpError> {
+        let url = path::expand_template(&self.url, &self.path_params)?;
+        let query = query::encode_query(&self.query, self.array_format);
+        Ok(query::append_query(&url, &query))
+    }
+    
+    /// Unexpanded path template (e.g. `/users/{id}`), usable as a metrics or log label
+    pub fn path_template(&self) -> Option<&str> {
+        if path::is_template(&self.url) {
+            Some(&self.url)
+        } else {
+            None
+        }
+    }
+}
+
+type SharedResult = Shared<LocalBoxFuture<'static, Result<HttpResponse, HttpError>>>;
+
+/// Error handlers of the callers waiting on a coalesced request, with their configs
+type JoinedHandlers = Rc<RefCell<Vec<(ErrorHandler, RequestConfig)>>>;
+
+/// A coalesced request and the error handlers to call when it fails
+struct InFlight {
+    shared: SharedResult,
+    handlers: JoinedHandlers,
+}
+
+thread_local! {
+    /// Identical safe requests currently in flight, shared by all clients
+    static IN_FLIGHT: RefCell<HashMap<String, InFlight>> = RefCell::new(HashMap::new());
+}
+
+/// Handler invoked with every final request error and the configuration that produced it
+pub type ErrorHandler = Callback<(HttpError, RequestConfig)>;
+
 /// HTTP request builder with fluent API
 #[derive(Debug, Clone)]
 pub struct RequestBuilder {
     config: RequestConfig,
     dispatch: Option<StoreDispatcher>,
+    error_handler: Option<ErrorHandler>,
+    scheduler: Option<RequestScheduler>,
+    rate_limiter: Option<RateLimiter>,
+    circuit_breaker: Option<CircuitBreaker>,
 }
 
 impl RequestBuilder {
@@ -216,6 +409,10 @@ impl RequestBuilder {
                 ..Default::default()
             },
             dispatch: None,
+            error_handler: None,
+            scheduler: None,
+            rate_limiter: None,
+            circuit_breaker: None,
         }
     }
     
@@ -225,6 +422,23 @@ impl RequestBuilder {
         self
     }
     
+    /// Set the handler that receives the final error of this request
+    pub fn on_error<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&HttpError, &RequestConfig) + 'static,
+    {
+        self.error_handler = Some(Callback::from(move |(error, config): (HttpError, RequestConfig)| {
+            handler(&error, &config)
+        }));
+        self
+    }
+    
+    /// Skip the client's global error handler for this request
+    pub fn without_error_handler(mut self) -> Self {
+        self.error_handler = None;
+        self
+    }
+    
     /// Add a header to the request
     pub fn header(mut self, name: &str, value: &str) -> Self {
         self.config.headers.insert(name.to_string(), value.to_string());
@@ -237,6 +451,72 @@ impl RequestBuilder {
         self
     }
     
+    /// Request a byte range; `end` is inclusive and `None` reads to the end
+    pub fn range(self, start: u64, end: Option<u64>) -> Self {
+        self.header("Range", &download::range_header(start, end))
+    }
+    
+    /// Only apply the `Range` header if the resource still matches `validator` (an ETag or date)
+    pub fn if_range(self, validator: &str) -> Self {
+        self.header("If-Range", validator)
+    }
+    
+    /// Substitute a `{name}` placeholder in the URL with a percent-encoded value
+    pub fn path_param(mut self, name: &str, value: impl ToString) -> Self {
+        self.config.path_params.insert(name.to_string(), value.to_string());
+        self
+    }
+    
+    /// Substitute placeholders with the fields of a serializable struct or map
+    pub fn path_params<T: Serialize>(mut self, params: &T) -> Result<Self, HttpError> {
+        let params = path::to_path_params(params)?;
+        self.config.path_params.extend(params);
+        Ok(self)
+    }
+    
+    /// Add a query parameter, percent-encoding key and value
+    pub fn query(mut self, key: &str, value: impl ToString) -> Self {
+        self.config.query.push((key.to_string(), QueryValue::Single(value.to_string())));
+        self
+    }
+    
+    /// Add a query parameter with several values, written in the request's [`ArrayFormat`]
+    pub fn query_array<I, V>(mut self, key: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: ToString,
+    {
+        let values = values.into_iter().map(|v| v.to_string()).collect();
+        self.config.query.push((key.to_string(), QueryValue::List(values)));
+        self
+    }
+    
+    /// Add several query parameters
+    pub fn query_pairs<I, K, V>(mut self, pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: ToString,
+    {
+        for (key, value) in pairs {
+            self.config.query.push((key.as_ref().to_string(), QueryValue::Single(value.to_string())));
+        }
+        self
+    }
+    
+    /// Add the fields of a serializable struct or map as query parameters
+    pub fn query_struct<T: Serialize>(mut self, data: &T) -> Result<Self, HttpError> {
+        let params = query::to_query_params(data)?;
+        self.config.query.extend(params);
+        Ok(self)
+    }
+    
+    /// Set how array query parameters are written (defaults to `a=1&a=2`)
+    pub fn array_format(mut self, format: ArrayFormat) -> Self {
+        self.config.array_format = format;
+        self
+    }
+    
     /// Set Content-Type header to application/json and serialize body as JSON
     pub fn json<T: Serialize>(mut self, data: &T) -> Result<Self, HttpError> {
         let json_string = serde_json::to_string(data).map_err(|e| HttpError::Serialization {
@@ -248,12 +528,33 @@ impl RequestBuilder {
         Ok(self)
     }
     
+    /// Set Content-Type header to application/x-www-form-urlencoded and encode body as a form
+    ///
+    /// Sequences are written as repeated keys (`a=1&a=2`) and nested maps as `parent[child]`.
+    pub fn form<T: Serialize>(mut self, data: &T) -> Result<Self, HttpError> {
+        let params = query::to_query_params(data)?;
+        
+        self.config.headers.insert(
+            "Content-Type".to_string(),
+            "application/x-www-form-urlencoded".to_string(),
+        );
+        self.config.body = RequestBody::UrlEncoded(query::encode_form(&params));
+        Ok(self)
+    }
+    
     /// Set body as form data
     pub fn form_data(mut self, form: web_sys::FormData) -> Self {
         self.config.body = RequestBody::FormData(form);
         self
     }
     
+    /// Set body as `multipart/form-data` built from a [`Multipart`]
+    pub fn multipart(mut self, multipart: Multipart) -> Self {
+        self.config.headers.insert("Content-Type".to_string(), multipart.content_type());
+        self.config.body = RequestBody::Binary(multipart.to_bytes());
+        self
+    }
+    
     /// Set body as plain text
     pub fn text(mut self, text: &str) -> Self {
         self.config.body = RequestBody::Text(text.to_string());
@@ -309,8 +610,146 @@ impl RequestBuilder {
         self
     }
     
+    /// Set the priority used when waiting for a free slot in the client's scheduler
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.config.priority = priority;
+        self
+    }
+    
+    /// Send a generated `Idempotency-Key` so that retries of this request are safe
+    ///
+    /// The key is generated once and sent unchanged on every retry attempt,
+    /// so the server can tell retries of a POST or PATCH apart from new
+    /// requests. Keyed requests can also be hedged.
+    pub fn idempotent(mut self) -> Self {
+        if self.config.idempotency_key.is_none() {
+            self.config.idempotency_key = Some(random::uuid_v4());
+        }
+        self
+    }
+    
+    /// Send the given `Idempotency-Key` on every attempt of this request
+    pub fn idempotency_key(mut self, key: &str) -> Self {
+        self.config.idempotency_key = Some(key.to_string());
+        self
+    }
+    
+    /// Fire up to `max_extra` duplicate requests, one every `after_ms`, while
+    /// no response has arrived, and take the first one that succeeds
+    ///
+    /// The remaining attempts are aborted, as they are on an error that a
+    /// duplicate would repeat, such as a 4xx status. Hedging only applies to
+    /// requests that are safe to repeat (idempotent methods or an idempotency
+    /// key).
+    pub fn hedge(mut self, after_ms: u32, max_extra: u32) -> Self {
+        self.config.hedge = Some(HedgePolicy { after_ms, max_extra });
+        self
+    }
+    
+    /// Coalesce this request with identical in-flight GET/HEAD/OPTIONS requests
+    pub fn deduplicate(mut self, enabled: bool) -> Self {
+        self.config.deduplicate = enabled;
+        self
+    }
+    
     /// Send the request
-    pub async fn send(self) -> Result<HttpResponse, HttpError> {
+    pub async fn send(mut self) -> Result<HttpResponse, HttpError> {
+        if let Some(key) = self.dedupe_key() {
+            // The shared request reports its error once to every distinct handler
+            return self.send_coalesced(key).await;
+        }
+        
+        let result = match self.apply_compression().await {
+            Ok(()) => self.send_with_retries().await,
+            Err(error) => Err(error),
+        };
+        self.emit_error(&result);
+        result
+    }
+    
+    /// Report the final error (after all retries) to the error handler
+    fn emit_error<T>(&self, result: &Result<T, HttpError>) {
+        if let (Err(error), Some(handler)) = (result, &self.error_handler) {
+            handler.emit((error.clone(), self.config.clone()));
+        }
+    }
+    
+    /// Notify and call the error handler once for an outcome decided after
+    /// the response was read, e.g. by a protocol layered over HTTP
+    ///
+    /// The request itself should have been sent with notifications and the
+    /// error handler disabled.
+    pub(crate) fn report_outcome<T>(&self, result: &Result<T, HttpError>) {
+        if self.config.with_notifications {
+            if let Some(ref dispatch) = self.dispatch {
+                let message = match result {
+                    Ok(_) => "Request completed successfully".to_string(),
+                    Err(error) => format!("Request failed: {}", error),
+                };
+                dispatch.emit(AppAction::ShowNotification(message));
+            }
+        }
+        
+        self.emit_error(result);
+    }
+    
+    /// Key identifying identical requests, if this request may be coalesced
+    fn dedupe_key(&self) -> Option<String> {
+        if !self.config.deduplicate || !self.config.method.is_safe() {
+            return None;
+        }
+        if !matches!(self.config.body, RequestBody::None) {
+            return None;
+        }
+        
+        let mut headers: Vec<_> = self.config.headers.iter()
+            .map(|(k, v)| format!("{}:{}", k.to_lowercase(), v))
+            .collect();
+        headers.sort();
+        
+        let url = self.config.request_url().ok()?;
+        let config = &self.config;
+        
+        // Requests that would be validated, retried, throttled or shown
+        // differently must not share a response; the error handler is not
+        // part of the key since every joined caller's handler is called
+        Some(format!(
+            "{} {} {}\nloader={} progress={} notifications={} dispatch={} origins={:?} timeout={:?} \
+             retries={}/{} hedge={:?} scheduler={:?} limiter={:?} breaker={:?}",
+            config.method.as_str(),
+            url,
+            headers.join("\n"),
+            config.with_loader,
+            config.with_progress,
+            config.with_notifications,
+            self.dispatch.is_some(),
+            config.allowed_origins,
+            config.timeout_ms,
+            config.retry_count,
+            config.retry_delay_ms,
+            config.hedge,
+            self.scheduler.as_ref().map(RequestScheduler::identity),
+            self.rate_limiter.as_ref().map(RateLimiter::identity),
+            self.circuit_breaker.as_ref().map(CircuitBreaker::identity),
+        ))
+    }
+    
+    /// Await the in-flight request with the same key, or start it if there is none
+    async fn send_coalesced(&self, key: String) -> Result<HttpResponse, HttpError> {
+        let builder = self.clone();
+        let handler = self.error_handler.clone().map(|handler| (handler, self.config.clone()));
+        let shared = coalesce(key, handler, move || {
+            async move { builder.send_with_retries().await }.boxed_local()
+        });
+        
+        let mut result = shared.await;
+        if let Ok(ref mut response) = result {
+            response.call_name = self.config.call_name.clone();
+        }
+        result
+    }
+    
+    async fn send_with_retries(&self) -> Result<HttpResponse, HttpError> {
         let mut last_error = None;
         
         for attempt in 0..=(self.config.retry_count) {
@@ -323,18 +762,19 @@ impl RequestBuilder {
                 Err(e) => {
                     last_error = Some(e.clone());
                     
-                    // Don't retry on certain error types
-                    match &e {
-                        HttpError::Cancelled | HttpError::InvalidUrl { .. } | HttpError::Configuration { .. } => {
-                            return Err(e);
-                        }
-                        HttpError::Http { status, .. } if *status >= 400 && *status < 500 => {
-                            return Err(e); // Don't retry client errors
-                        }
-                        _ => {
-                            if attempt < self.config.retry_count {
-                                log!("Request failed, retrying... (attempt {} of {})", attempt + 1, self.config.retry_count + 1);
-                            }
+                    // Don't retry errors another attempt would repeat
+                    if is_definitive(&e) {
+                        return Err(e);
+                    }
+                    
+                    if attempt < self.config.retry_count {
+                        log!("Request failed, retrying... (attempt {} of {})", attempt + 1, self.config.retry_count + 1);
+                        if attempt == 0 && !self.is_retryable() {
+                            // The retry could repeat side effects the server cannot detect
+                            log!(format!(
+                                "Retrying {} request without an Idempotency-Key; use idempotent() so the server can deduplicate it",
+                                self.config.method.as_str()
+                            ));
                         }
                     }
                 }
@@ -344,7 +784,35 @@ impl RequestBuilder {
         Err(last_error.unwrap_or(HttpError::Network { message: "Unknown error".to_string() }))
     }
     
+    /// Whether the request can be repeated without duplicating side effects
+    ///
+    /// Hedging requires it; retries are sent regardless, with a warning.
+    fn is_retryable(&self) -> bool {
+        self.config.method.is_idempotent() || self.config.idempotency_key.is_some()
+    }
+    
     async fn execute_request(&self) -> Result<HttpResponse, HttpError> {
+        self.gated(self.throttled_request(), |response| response.status).await
+    }
+    
+    /// Open a streaming response with the same loader, notification and
+    /// circuit breaker handling as a buffered request
+    ///
+    /// The scheduler slot is returned with the response so that it can be
+    /// held until the body has been read.
+    pub(crate) async fn execute_stream(&self) -> Result<(reqwasm::http::Response, Option<scheduler::SchedulerPermit>), HttpError> {
+        let request = async {
+            let permit = self.acquire_slot().await;
+            self.open_stream(None).await.map(|response| (response, permit))
+        };
+        self.gated(request, |(response, _)| response.status()).await
+    }
+    
+    /// Run `request` with loader, progress, notifications and the circuit breaker
+    async fn gated<T, F>(&self, request: F, status_of: impl Fn(&T) -> u16) -> Result<T, HttpError>
+    where
+        F: std::future::Future<Output = Result<T, HttpError>>,
+    {
         // Enable loader if requested
         if self.config.with_loader {
             if let Some(ref dispatch) = self.dispatch {
@@ -359,18 +827,30 @@ impl RequestBuilder {
             }
         }
         
-        let result = self.make_request().await;
+        // Fail fast while the circuit for this endpoint is open
+        let result = match self.circuit_breaker {
+            Some(ref breaker) => match breaker.check(&self.target_url(), js_sys::Date::now()) {
+                Ok(permit) => {
+                    let result = request.await;
+                    permit.record(&result, js_sys::Date::now());
+                    result
+                }
+                Err(error) => Err(error),
+            },
+            None => request.await,
+        };
         
         // Handle result and update state
         match &result {
             Ok(response) => {
                 if self.config.with_notifications {
                     if let Some(ref dispatch) = self.dispatch {
-                        if response.is_success() {
-                            let message = format!("Request completed successfully ({})", response.status);
+                        let status = status_of(response);
+                        if (200..300).contains(&status) {
+                            let message = format!("Request completed successfully ({})", status);
                             dispatch.emit(AppAction::ShowNotification(message));
                         } else {
-                            let message = format!("Request failed with status {}", response.status);
+                            let message = format!("Request failed with status {}", status);
                             dispatch.emit(AppAction::ShowNotification(message));
                         }
                     }
@@ -402,21 +882,87 @@ impl RequestBuilder {
         result
     }
     
-    async fn make_request(&self) -> Result<HttpResponse, HttpError> {
-        // Validate URL
-        if self.config.url.is_empty() {
-            return Err(HttpError::InvalidUrl { url: self.config.url.clone() });
+    /// Request URL for rate limiting and circuit breaking, falling back to the template
+    fn target_url(&self) -> String {
+        self.config.request_url().unwrap_or_else(|_| self.config.url.clone())
+    }
+    
+    /// Make the request once the rate limiter and scheduler allow it
+    async fn throttled_request(&self) -> Result<HttpResponse, HttpError> {
+        match self.config.hedge {
+            Some(policy) if policy.max_extra > 0 && self.is_retryable() => {
+                self.make_hedged_request(policy).await
+            }
+            _ => {
+                let _permit = self.acquire_slot().await;
+                self.make_request(None).await
+            }
+        }
+    }
+    
+    /// Wait for the rate limiter and a scheduler slot; the slot is held until the permit drops
+    async fn acquire_slot(&self) -> Option<scheduler::SchedulerPermit> {
+        // Delay the request if it would exceed the client's rate limits
+        if let Some(ref rate_limiter) = self.rate_limiter {
+            rate_limiter.wait(&self.target_url()).await;
         }
         
+        // Wait for a free slot if the client limits concurrency
+        match self.scheduler {
+            Some(ref scheduler) => Some(scheduler.acquire(self.config.priority).await),
+            None => None,
+        }
+    }
+    
+    /// Race the original request against delayed duplicates and abort the losers
+    ///
+    /// The first successful attempt wins. A 4xx or other definitive error ends
+    /// the race at once; transient failures only fail the request once every
+    /// attempt has failed. Each attempt goes through the rate limiter and
+    /// scheduler on its own.
+    async fn make_hedged_request(&self, policy: HedgePolicy) -> Result<HttpResponse, HttpError> {
+        let controllers: Vec<Option<web_sys::AbortController>> = (0..=policy.max_extra)
+            .map(|_| web_sys::AbortController::new().ok())
+            .collect();
+        
+        let attempts = controllers.iter().enumerate().map(|(attempt, controller)| {
+            let signal = controller.as_ref().map(|c| c.signal());
+            async move {
+                if attempt > 0 {
+                    gloo_timers::future::TimeoutFuture::new(policy.after_ms * attempt as u32).await;
+                }
+                let _permit = self.acquire_slot().await;
+                let mut response = self.make_request(signal.as_ref()).await?;
+                response.hedge_attempt = Some(attempt as u32);
+                Ok(response)
+            }
+            .boxed_local()
+        });
+        
+        let result = first_success(attempts, is_definitive).await;
+        
+        for controller in controllers.iter().flatten() {
+            controller.abort();
+        }
+        
+        result
+    }
+    
+    /// Build the reqwasm request with the resolved URL, headers and body
+    pub(crate) fn build_request(&self, abort_signal: Option<&web_sys::AbortSignal>) -> Result<Request, HttpError> {
+        // Resolve and validate URL
+        let url = self.config.request_url()?;
+        resolve::validate_url(&url, &self.config.allowed_origins)?;
+        
         // Build request using reqwasm
         let mut request = match self.config.method {
-            HttpMethod::Get => Request::get(&self.config.url),
-            HttpMethod::Post => Request::post(&self.config.url),
-            HttpMethod::Put => Request::put(&self.config.url),
-            HttpMethod::Delete => Request::delete(&self.config.url),
-            HttpMethod::Patch => Request::new(&self.config.url).method(Method::PATCH),
-            HttpMethod::Head => Request::new(&self.config.url).method(Method::HEAD),
-            HttpMethod::Options => Request::new(&self.config.url).method(Method::OPTIONS),
+            HttpMethod::Get => Request::get(&url),
+            HttpMethod::Post => Request::post(&url),
+            HttpMethod::Put => Request::put(&url),
+            HttpMethod::Delete => Request::delete(&url),
+            HttpMethod::Patch => Request::new(&url).method(Method::PATCH),
+            HttpMethod::Head => Request::new(&url).method(Method::HEAD),
+            HttpMethod::Options => Request::new(&url).method(Method::OPTIONS),
         };
         
         // Set headers
@@ -424,6 +970,14 @@ impl RequestBuilder {
             request = request.header(key, value);
         }
         
+        if let Some(ref key) = self.config.idempotency_key {
+            request = request.header("Idempotency-Key", key);
+        }
+        
+        if abort_signal.is_some() {
+            request = request.abort_signal(abort_signal);
+        }
+        
         // Set body based on type
         match &self.config.body {
             RequestBody::None => {},
@@ -433,6 +987,9 @@ impl RequestBuilder {
             RequestBody::Json(json) => {
                 request = request.body(json);
             },
+            RequestBody::UrlEncoded(form) => {
+                request = request.body(form);
+            },
             RequestBody::FormData(form) => {
                 request = request.body(form);
             },
@@ -442,6 +999,34 @@ impl RequestBuilder {
             },
         }
         
+        Ok(request)
+    }
+    
+    /// Send the request and return the response without reading its body
+    ///
+    /// Used by streaming consumers; non-2xx responses are read and turned
+    /// into [`HttpError::Http`].
+    pub(crate) async fn open_stream(&self, abort_signal: Option<&web_sys::AbortSignal>) -> Result<reqwasm::http::Response, HttpError> {
+        let request = self.build_request(abort_signal)?;
+        let response = request.send().await.map_err(|e| HttpError::Network {
+            message: format!("Request failed: {:?}", e),
+        })?;
+        
+        let status = response.status();
+        if !(200..300).contains(&status) {
+            return Err(HttpError::Http {
+                status,
+                message: format!("HTTP error {}", status),
+                body: response.text().await.ok(),
+            });
+        }
+        
+        Ok(response)
+    }
+    
+    async fn make_request(&self, abort_signal: Option<&web_sys::AbortSignal>) -> Result<HttpResponse, HttpError> {
+        let request = self.build_request(abort_signal)?;
+        
         // Update progress if enabled
         if self.config.with_progress {
             if let Some(ref dispatch) = self.dispatch {
@@ -459,28 +1044,23 @@ impl RequestBuilder {
         let url = response.url();
         
         // Extract headers
-        let header_map = HashMap::new();
-        // Note: reqwasm doesn't expose headers directly, this is a limitation
+        let header_map: HashMap<String, String> = response.headers().entries().collect();
         
         // Get response body
-        let body = response.text().await.map_err(|e| HttpError::Network {
+        let bytes = response.binary().await.map_err(|e| HttpError::Network {
             message: format!("Failed to read response body: {:?}", e),
         })?;
-        
-        let http_response = HttpResponse {
-            status,
-            headers: header_map,
-            body,
-            url,
-            call_name: self.config.call_name.clone(),
-        };
+        let mut http_response = HttpResponse::new(status, &url, bytes);
+        http_response.headers = header_map;
+        http_response.call_name = self.config.call_name.clone();
+        http_response.path_template = self.config.path_template().map(str::to_string);
         
         // Check if response indicates an error
         if !http_response.is_success() {
             return Err(HttpError::Http {
                 status,
                 message: format!("HTTP error {}", status),
-                body: Some(http_response.body.clone()),
+                body: Some(http_response.text().to_string()),
             });
         }
         
@@ -488,6 +1068,84 @@ impl RequestBuilder {
     }
 }
 
+/// Share the in-flight request under `key`, or start one with `start`
+///
+/// `handler` is called with the caller's config if the request fails, once
+/// per distinct handler however many callers share it. The entry is removed
+/// once the shared request completes.
+fn coalesce<F>(key: String, handler: Option<(ErrorHandler, RequestConfig)>, start: F) -> SharedResult
+where
+    F: FnOnce() -> LocalBoxFuture<'static, Result<HttpResponse, HttpError>>,
+{
+    let joined = IN_FLIGHT.with(|in_flight| {
+        in_flight.borrow().get(&key).map(|entry| (entry.shared.clone(), entry.handlers.clone()))
+    });
+    if let Some((shared, handlers)) = joined {
+        if let Some(handler) = handler {
+            let mut handlers = handlers.borrow_mut();
+            if !handlers.iter().any(|(known, _)| *known == handler.0) {
+                handlers.push(handler);
+            }
+        }
+        return shared;
+    }
+    
+    let handlers: JoinedHandlers = Rc::new(RefCell::new(handler.into_iter().collect()));
+    let request = start();
+    let cleanup_key = key.clone();
+    let report_to = handlers.clone();
+    let shared = async move {
+        let result = request.await;
+        IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().remove(&cleanup_key));
+        if let Err(ref error) = result {
+            for (handler, config) in report_to.take() {
+                handler.emit((error.clone(), config));
+            }
+        }
+        result
+    }
+    .boxed_local()
+    .shared();
+    
+    IN_FLIGHT.with(|in_flight| in_flight.borrow_mut().insert(key, InFlight { shared: shared.clone(), handlers }));
+    shared
+}
+
+/// Whether another attempt would fail the same way, so retrying or hedging is pointless
+fn is_definitive(error: &HttpError) -> bool {
+    match error {
+        HttpError::Cancelled
+        | HttpError::InvalidUrl { .. }
+        | HttpError::Configuration { .. }
+        | HttpError::CircuitOpen { .. } => true,
+        // Client errors
+        HttpError::Http { status, .. } => *status >= 400 && *status < 500,
+        _ => false,
+    }
+}
+
+/// Poll all attempts until one succeeds, or return the last error once all have failed
+///
+/// An error for which `ends_race` holds is returned at once; the attempts
+/// still pending are dropped.
+async fn first_success<'a, T, E, I>(attempts: I, ends_race: impl Fn(&E) -> bool) -> Result<T, E>
+where
+    I: IntoIterator<Item = LocalBoxFuture<'a, Result<T, E>>>,
+{
+    let mut pending: futures::stream::FuturesUnordered<_> = attempts.into_iter().collect();
+    let mut last_error = None;
+    
+    while let Some(result) = futures::StreamExt::next(&mut pending).await {
+        match result {
+            Ok(value) => return Ok(value),
+            Err(error) if ends_race(&error) => return Err(error),
+            Err(error) => last_error = Some(error),
+        }
+    }
+    
+    Err(last_error.expect("at least one attempt"))
+}
+
 /// Main HTTP client with fluent API
 #[derive(Debug, Clone)]
 pub struct HttpClient {
@@ -495,6 +1153,12 @@ pub struct HttpClient {
     default_headers: HashMap<String, String>,
     default_timeout_ms: Option<u32>,
     dispatch: Option<StoreDispatcher>,
+    error_handler: Option<ErrorHandler>,
+    deduplicate: bool,
+    scheduler: Option<RequestScheduler>,
+    rate_limiter: Option<RateLimiter>,
+    circuit_breaker: Option<CircuitBreaker>,
+    allowed_origins: Vec<String>,
 }
 
 impl HttpClient {
@@ -505,6 +1169,12 @@ impl HttpClient {
             default_headers: HashMap::new(),
             default_timeout_ms: Some(30000),
             dispatch: None,
+            error_handler: None,
+            deduplicate: false,
+            scheduler: None,
+            rate_limiter: None,
+            circuit_breaker: None,
+            allowed_origins: Vec::new(),
         }
     }
     
@@ -515,6 +1185,12 @@ impl HttpClient {
             default_headers: HashMap::new(),
             default_timeout_ms: Some(30000),
             dispatch: Some(dispatch),
+            error_handler: None,
+            deduplicate: false,
+            scheduler: None,
+            rate_limiter: None,
+            circuit_breaker: None,
+            allowed_origins: Vec::new(),
         }
     }
     
@@ -536,24 +1212,113 @@ impl HttpClient {
         self
     }
     
+    /// Register a global handler for final request errors (after retries)
+    ///
+    /// The handler receives the error together with the request configuration,
+    /// which makes it a single place to react to e.g. 401 or 503 responses.
+    pub fn on_error<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&HttpError, &RequestConfig) + 'static,
+    {
+        self.error_handler = Some(Callback::from(move |(error, config): (HttpError, RequestConfig)| {
+            handler(&error, &config)
+        }));
+        self
+    }
+    
+    /// Coalesce identical in-flight GET/HEAD/OPTIONS requests
+    ///
+    /// Requests with the same method, URL, headers and request settings share
+    /// one network call, even when they come from different clients (e.g. one
+    /// per component), as long as those share their scheduler, rate limiter
+    /// and circuit breaker. Every caller receives a clone of the response or
+    /// error, and every distinct error handler is called once.
+    pub fn deduplicate(mut self, enabled: bool) -> Self {
+        self.deduplicate = enabled;
+        self
+    }
+    
+    /// Limit the number of concurrent requests made through this client
+    ///
+    /// Requests over the limit are queued by their [`Priority`].
+    pub fn max_in_flight(mut self, max: usize) -> Self {
+        self.scheduler = Some(RequestScheduler::new(max));
+        self
+    }
+    
+    /// Share a scheduler with other clients to bound their combined concurrency
+    pub fn scheduler(mut self, scheduler: RequestScheduler) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+    
+    /// Rate limit all requests made through this client
+    ///
+    /// Requests over the limit are delayed rather than sent and rejected with 429.
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        let rate_limiter = self.rate_limiter.take().unwrap_or_default();
+        self.rate_limiter = Some(rate_limiter.limit(limit));
+        self
+    }
+    
+    /// Rate limit requests to a single host (e.g. `api.example.com`)
+    pub fn rate_limit_host(mut self, host: &str, limit: RateLimit) -> Self {
+        let rate_limiter = self.rate_limiter.take().unwrap_or_default();
+        self.rate_limiter = Some(rate_limiter.host_limit(host, limit));
+        self
+    }
+    
+    /// Share a rate limiter with other clients
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+    
+    /// Fail fast with [`HttpError::CircuitOpen`] while an endpoint keeps failing
+    ///
+    /// Keep a clone of the breaker to observe circuit states, e.g. for a status banner.
+    pub fn circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+    
+    /// Only allow requests to the given origins (e.g. `https://api.example.com`)
+    ///
+    /// Requests to other origins fail with [`HttpError::InvalidUrl`]. Relative
+    /// URLs are resolved against the page and are always allowed.
+    pub fn allowed_origins<I, S>(mut self, origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.allowed_origins = origins.into_iter().map(|o| resolve::normalize_origin(o.as_ref())).collect();
+        self
+    }
+    
     /// Build URL with optional base URL
+    ///
+    /// Paths are resolved below an absolute base URL following the WHATWG URL
+    /// rules. A relative base such as `/api` is joined by concatenation and
+    /// resolved by the browser against the page; an invalid absolute base is
+    /// concatenated too, and the request then fails validation with
+    /// [`HttpError::InvalidUrl`] when sent.
     fn build_url(&self, path: &str) -> String {
         match &self.base_url {
-            Some(base) => {
-                if path.starts_with("http://") || path.starts_with("https://") {
-                    path.to_string()
-                } else {
-                    format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
-                }
-            }
+            Some(base) => resolve::join_url(base, path).unwrap_or_else(|_| {
+                format!("{}/{}", base.trim_end_matches('/'), path.trim_start_matches('/'))
+            }),
             None => path.to_string(),
         }
     }
     
     /// Create request builder with defaults applied
     fn create_builder(&self, method: HttpMethod, path: &str) -> RequestBuilder {
-        let url = self.build_url(path);
-        let mut builder = RequestBuilder::new(method, &url);
+        self.builder_for_url(method, &self.build_url(path))
+    }
+    
+    /// Create request builder with defaults applied for an already resolved URL
+    pub(crate) fn builder_for_url(&self, method: HttpMethod, url: &str) -> RequestBuilder {
+        let mut builder = RequestBuilder::new(method, url);
         
         // Apply default headers
         builder.config.headers.extend(self.default_headers.clone());
@@ -568,6 +1333,14 @@ impl HttpClient {
             builder = builder.with_dispatcher(dispatch.clone());
         }
         
+        // Apply global error handler
+        builder.error_handler = self.error_handler.clone();
+        builder.config.deduplicate = self.deduplicate;
+        builder.scheduler = self.scheduler.clone();
+        builder.rate_limiter = self.rate_limiter.clone();
+        builder.circuit_breaker = self.circuit_breaker.clone();
+        builder.config.allowed_origins = self.allowed_origins.clone();
+        
         builder
     }
     
@@ -660,30 +1433,20 @@ pub mod utils {
         content_type: &str,
         with_progress: bool,
     ) -> Result<HttpResponse, HttpError> {
-        let form_data = web_sys::FormData::new().map_err(|_| HttpError::Configuration {
-            message: "Failed to create FormData".to_string(),
-        })?;
-        
-        let blob_options = web_sys::BlobPropertyBag::new();
-        blob_options.set_type(content_type);
-        
-        let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
-            &js_sys::Array::of1(&js_sys::Uint8Array::from(file_data)),
-            &blob_options,
-        ).map_err(|_| HttpError::Configuration {
-            message: "Failed to create blob".to_string(),
-        })?;
-        
-        form_data.append_with_blob_and_filename("file", &blob, filename).map_err(|_| {
-            HttpError::Configuration {
-                message: "Failed to append file to FormData".to_string(),
-            }
-        })?;
-        
+        let multipart = Multipart::new().file("file", file_data.to_vec(), filename, content_type);
+        upload_multipart(url, multipart, with_progress).await
+    }
+    
+    /// Upload a multipart body (fields and any number of files) with progress tracking
+    pub async fn upload_multipart(
+        url: &str,
+        multipart: Multipart,
+        with_progress: bool,
+    ) -> Result<HttpResponse, HttpError> {
         let client = get_http_client();
         client
             .post(url)
-            .form_data(form_data)
+            .multipart(multipart)
             .with_progress(with_progress)
             .send()
             .await
@@ -693,10 +1456,20 @@ pub mod utils {
     pub async fn download_file(url: &str) -> Result<Vec<u8>, HttpError> {
         let client = get_http_client();
         let response = client.get(url).send().await?;
-        
-        // Convert text response to bytes (this is a simplified implementation)
-        // In a real implementation, you'd want to handle binary data properly
-        Ok(response.body.into_bytes())
+        Ok(response.bytes)
+    }
+    
+    /// Download a file in byte ranges, in parallel when the server supports it
+    pub async fn download_file_ranged(url: &str, parallel: usize) -> Result<Vec<u8>, HttpError> {
+        let client = get_http_client();
+        client.download(url).parallel(parallel).start().await
+    }
+    
+    /// Download a file and save it in the browser under the given or server-provided name
+    pub async fn save_file(url: &str, filename: Option<&str>) -> Result<(), HttpError> {
+        let client = get_http_client();
+        client.save_file(url, filename).await?;
+        Ok(())
     }
 }
 
@@ -0,0 +1,230 @@
+//! Pure-Rust `multipart/form-data` builder
+//!
+//! Unlike `web_sys::FormData`, a [`Multipart`] body can be inspected, tested
+//! and serialized off-browser. It supports text fields, file parts with a
+//! filename, content type and custom part headers, and several files under
+//! the same field name. In the browser it can be converted to `FormData` when
+//! the platform should pick the boundary itself.
+
+use crate::{random, HttpError};
+
+/// A single part of a multipart body
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    headers: Vec<(String, String)>,
+    data: Vec<u8>,
+}
+
+impl Part {
+    /// Text field part
+    pub fn text(name: &str, value: &str) -> Self {
+        Self::bytes(name, value.as_bytes().to_vec())
+    }
+
+    /// Part with raw bytes
+    pub fn bytes(name: &str, data: Vec<u8>) -> Self {
+        Self {
+            name: name.to_string(),
+            filename: None,
+            content_type: None,
+            headers: Vec::new(),
+            data,
+        }
+    }
+
+    /// Set the filename, marking the part as a file upload
+    pub fn file_name(mut self, filename: &str) -> Self {
+        self.filename = Some(filename.to_string());
+        self
+    }
+
+    /// Set the part's Content-Type
+    pub fn content_type(mut self, content_type: &str) -> Self {
+        self.content_type = Some(content_type.to_string());
+        self
+    }
+
+    /// Add a custom part header
+    ///
+    /// Characters that are not allowed in header names are dropped and line
+    /// breaks in the value are replaced with spaces, so a part header can
+    /// never start a new header line.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Field name of the part
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Filename of the part, if it is a file
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Content of the part
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn write_to(&self, boundary: &str, out: &mut Vec<u8>) {
+        out.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+
+        let mut disposition = format!("Content-Disposition: form-data; name=\"{}\"", escape_quoted(&self.name));
+        if let Some(ref filename) = self.filename {
+            disposition.push_str(&format!("; filename=\"{}\"", escape_quoted(filename)));
+        }
+        out.extend_from_slice(disposition.as_bytes());
+        out.extend_from_slice(b"\r\n");
+
+        let content_type = match (&self.content_type, &self.filename) {
+            (Some(content_type), _) => Some(content_type.as_str()),
+            (None, Some(_)) => Some("application/octet-stream"),
+            (None, None) => None,
+        };
+        if let Some(content_type) = content_type {
+            out.extend_from_slice(format!("Content-Type: {}\r\n", header_value(content_type)).as_bytes());
+        }
+
+        for (name, value) in &self.headers {
+            let name = header_name(name);
+            if !name.is_empty() {
+                out.extend_from_slice(format!("{}: {}\r\n", name, header_value(value)).as_bytes());
+            }
+        }
+
+        out.extend_from_slice(b"\r\n");
+        out.extend_from_slice(&self.data);
+        out.extend_from_slice(b"\r\n");
+    }
+}
+
+/// Builder for `multipart/form-data` bodies
+#[derive(Debug, Clone, PartialEq)]
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+impl Multipart {
+    /// Create an empty body with a randomly generated boundary
+    pub fn new() -> Self {
+        let suffix: String = random::random_bytes::<12>().iter().map(|b| format!("{:02x}", b)).collect();
+        Self::with_boundary(&format!("----httpcalls{}", suffix))
+    }
+
+    /// Create an empty body with a fixed boundary
+    pub fn with_boundary(boundary: &str) -> Self {
+        Self {
+            boundary: boundary.to_string(),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Add a text field
+    pub fn text(self, name: &str, value: &str) -> Self {
+        self.part(Part::text(name, value))
+    }
+
+    /// Add a file; call repeatedly with the same name to upload several files
+    pub fn file(self, name: &str, data: Vec<u8>, filename: &str, content_type: &str) -> Self {
+        self.part(Part::bytes(name, data).file_name(filename).content_type(content_type))
+    }
+
+    /// Add a custom part
+    pub fn part(mut self, part: Part) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    /// Boundary separating the parts
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Parts added so far
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+
+    /// Content-Type header value including the boundary
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Serialize the body
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.parts.iter().map(|p| p.data.len() + 128).sum());
+        for part in &self.parts {
+            part.write_to(&self.boundary, &mut out);
+        }
+        out.extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        out
+    }
+
+    /// Convert to browser `FormData`
+    ///
+    /// Custom part headers cannot be represented in `FormData` and are dropped.
+    pub fn to_form_data(&self) -> Result<web_sys::FormData, HttpError> {
+        let form_data = web_sys::FormData::new().map_err(|_| HttpError::Configuration {
+            message: "Failed to create FormData".to_string(),
+        })?;
+
+        for part in &self.parts {
+            let appended = match part.filename {
+                Some(ref filename) => {
+                    let options = web_sys::BlobPropertyBag::new();
+                    options.set_type(part.content_type.as_deref().unwrap_or("application/octet-stream"));
+
+                    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
+                        &js_sys::Array::of1(&js_sys::Uint8Array::from(part.data.as_slice())),
+                        &options,
+                    )
+                    .map_err(|_| HttpError::Configuration {
+                        message: "Failed to create blob".to_string(),
+                    })?;
+
+                    form_data.append_with_blob_and_filename(&part.name, &blob, filename)
+                }
+                None => form_data.append_with_str(&part.name, &String::from_utf8_lossy(&part.data)),
+            };
+
+            appended.map_err(|_| HttpError::Configuration {
+                message: format!("Failed to append part `{}` to FormData", part.name),
+            })?;
+        }
+
+        Ok(form_data)
+    }
+}
+
+impl Default for Multipart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Escape a field name or filename for a quoted header parameter
+fn escape_quoted(value: &str) -> String {
+    value
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+        .replace('"', "%22")
+}
+
+/// Keep only the characters allowed in a header name (RFC 9110 `tchar`)
+fn header_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(*c))
+        .collect()
+}
+
+/// Replace line breaks in a header value so it stays on one line
+fn header_value(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
@@ -0,0 +1,166 @@
+//! Streaming newline-delimited JSON (NDJSON / JSON Lines) decoding
+//!
+//! [`RequestBuilder::send_stream`] reads the response body chunk by chunk and
+//! yields every line as soon as it is complete, instead of buffering the whole
+//! body. A malformed line produces an [`HttpError::InvalidLine`] item carrying
+//! its line number; decoding continues with the next line.
+
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use futures::stream::{self, LocalBoxStream, StreamExt};
+use serde::de::DeserializeOwned;
+use crate::body_stream::BodyReader;
+use crate::scheduler::SchedulerPermit;
+use crate::{HttpError, RequestBuilder};
+
+/// Incremental decoder for newline-delimited JSON
+#[derive(Debug)]
+pub struct NdjsonDecoder<T> {
+    buffer: Vec<u8>,
+    /// Bytes of `buffer` already searched for a newline
+    scanned: usize,
+    line: usize,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> NdjsonDecoder<T> {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            scanned: 0,
+            line: 0,
+            _item: PhantomData,
+        }
+    }
+
+    /// Feed a chunk of the body, returning the values of the lines it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Result<T, HttpError>> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut items = Vec::new();
+        let mut start = 0;
+        while let Some(offset) = self.buffer[self.scanned..].iter().position(|&b| b == b'\n') {
+            let end = self.scanned + offset;
+            let line = self.buffer[start..end].to_vec();
+            items.extend(self.decode_line(&line));
+            start = end + 1;
+            self.scanned = start;
+        }
+
+        // Keep the incomplete last line without searching it again
+        self.buffer.drain(..start);
+        self.scanned = self.buffer.len();
+        items
+    }
+
+    /// Decode a final line that was not terminated by a newline
+    pub fn finish(&mut self) -> Option<Result<T, HttpError>> {
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let line = std::mem::take(&mut self.buffer);
+        self.scanned = 0;
+        self.decode_line(&line)
+    }
+
+    fn decode_line(&mut self, line: &[u8]) -> Option<Result<T, HttpError>> {
+        self.line += 1;
+
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return None;
+        }
+
+        Some(serde_json::from_slice(line).map_err(|e| HttpError::InvalidLine {
+            line: self.line,
+            message: e.to_string(),
+        }))
+    }
+}
+
+impl<T: DeserializeOwned> Default for NdjsonDecoder<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decoding state carried between stream items
+struct StreamState<T> {
+    reader: Option<BodyReader>,
+    /// Request whose error handler hears about the first stream error
+    request: Option<RequestBuilder>,
+    /// Scheduler slot, held until the body has been read
+    _permit: Option<SchedulerPermit>,
+    decoder: NdjsonDecoder<T>,
+    pending: VecDeque<Result<T, HttpError>>,
+}
+
+impl RequestBuilder {
+    /// Send the request and decode the response body as newline-delimited JSON
+    ///
+    /// Fails if the request itself fails; each decoded line is then yielded as
+    /// it arrives. Malformed lines yield [`HttpError::InvalidLine`] without
+    /// ending the stream, while a dropped connection ends it with an error.
+    ///
+    /// The request goes through the client's rate limiter, circuit breaker and
+    /// scheduler; its slot is held until the stream ends or is dropped. Loader
+    /// and notifications cover opening the stream. The error handler is called
+    /// if opening fails, or else for the first error item of the stream. The
+    /// body is compressed as [`RequestBuilder::send`] would.
+    pub async fn send_stream<T>(mut self) -> Result<LocalBoxStream<'static, Result<T, HttpError>>, HttpError>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let opened = match self.apply_compression().await {
+            Ok(()) => self
+                .execute_stream()
+                .await
+                .and_then(|(response, permit)| Ok((BodyReader::new(&response)?, permit))),
+            Err(error) => Err(error),
+        };
+        let (reader, permit) = match opened {
+            Ok(opened) => opened,
+            Err(error) => {
+                let result = Err(error);
+                self.emit_error(&result);
+                return result;
+            }
+        };
+
+        let state = StreamState {
+            reader: Some(reader),
+            request: Some(self),
+            _permit: permit,
+            decoder: NdjsonDecoder::new(),
+            pending: VecDeque::new(),
+        };
+
+        let items = stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(item) = state.pending.pop_front() {
+                    if item.is_err() {
+                        if let Some(request) = state.request.take() {
+                            request.emit_error(&item);
+                        }
+                    }
+                    return Some((item, state));
+                }
+
+                let reader = state.reader.as_ref()?;
+                match reader.next_chunk().await {
+                    Ok(Some(chunk)) => state.pending.extend(state.decoder.feed(&chunk)),
+                    Ok(None) => {
+                        state.reader = None;
+                        state.pending.extend(state.decoder.finish());
+                    }
+                    Err(error) => {
+                        state.reader = None;
+                        state.pending.push_back(Err(error));
+                    }
+                }
+            }
+        });
+
+        Ok(items.boxed_local())
+    }
+}
@@ -0,0 +1,104 @@
+//! Path templates with typed path parameters
+//!
+//! A request URL may contain `{name}` placeholders, e.g.
+//! `/users/{id}/posts/{post_id}`. Placeholders are replaced with
+//! percent-encoded parameter values when the request is sent; the unexpanded
+//! template stays available as a low-cardinality label for metrics and logs.
+
+use std::collections::HashMap;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::Serialize;
+use serde_json::Value;
+use crate::HttpError;
+
+/// Characters left unencoded in a path segment (RFC 3986 unreserved)
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Percent-encode a value for use as a single path segment
+pub fn encode_path_segment(input: &str) -> String {
+    utf8_percent_encode(input, PATH_SEGMENT).to_string()
+}
+
+/// Whether the URL contains `{name}` placeholders
+pub fn is_template(url: &str) -> bool {
+    placeholders(url).next().is_some()
+}
+
+/// Replace `{name}` placeholders with encoded parameter values
+///
+/// Fails with [`HttpError::InvalidUrl`] if a placeholder has no value.
+pub fn expand_template(template: &str, params: &HashMap<String, String>) -> Result<String, HttpError> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut last = 0;
+
+    for (start, end, name) in placeholders(template) {
+        let value = params.get(name).ok_or_else(|| HttpError::InvalidUrl {
+            url: template.to_string(),
+            reason: format!("missing value for path parameter `{}`", name),
+        })?;
+
+        expanded.push_str(&template[last..start]);
+        expanded.push_str(&encode_path_segment(value));
+        last = end;
+    }
+
+    expanded.push_str(&template[last..]);
+    Ok(expanded)
+}
+
+/// Convert a serializable struct or map into path parameters
+pub fn to_path_params<T: Serialize>(value: &T) -> Result<HashMap<String, String>, HttpError> {
+    let value = serde_json::to_value(value).map_err(|e| HttpError::Serialization {
+        message: format!("Failed to serialize path parameters: {}", e),
+    })?;
+
+    let Value::Object(map) = value else {
+        return Err(HttpError::Serialization {
+            message: "Path parameters must serialize to a struct or map".to_string(),
+        });
+    };
+
+    map.into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(s) => s,
+                Value::Bool(b) => b.to_string(),
+                Value::Number(n) => n.to_string(),
+                _ => {
+                    return Err(HttpError::Serialization {
+                        message: format!("Path parameter `{}` must be a string, number or boolean", key),
+                    })
+                }
+            };
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// Names of the `{name}` placeholders in a template, in order
+pub(crate) fn placeholder_names(template: &str) -> impl Iterator<Item = &str> {
+    placeholders(template).map(|(_, _, name)| name)
+}
+
+/// Iterate over `(start, end, name)` of every `{name}` placeholder
+fn placeholders(template: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    let mut offset = 0;
+
+    std::iter::from_fn(move || {
+        while let Some(open) = template[offset..].find('{') {
+            let start = offset + open;
+            let close = template[start..].find('}')? + start;
+            let name = &template[start + 1..close];
+            offset = close + 1;
+
+            if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Some((start, close + 1, name));
+            }
+        }
+        None
+    })
+}
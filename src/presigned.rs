@@ -0,0 +1,350 @@
+//! S3-style multipart uploads through presigned part URLs
+//!
+//! The data is sliced into parts which are uploaded directly to object
+//! storage with `PUT`. The URL for each part is obtained from a user-supplied
+//! callback (usually a call to your own backend that presigns it), parts are
+//! uploaded in parallel up to a concurrency limit and retried individually,
+//! and the collected ETags are handed to a completion callback that finishes
+//! the upload.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::rc::Rc;
+use futures::future::LocalBoxFuture;
+use futures::{FutureExt, StreamExt, TryStreamExt};
+use httpmessenger::AppAction;
+use wasm_bindgen_futures::JsFuture;
+use crate::{HttpClient, HttpError, HttpMethod};
+
+/// Default part size (8 MiB); S3 requires at least 5 MiB for all but the last part
+const DEFAULT_PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// Maximum number of parts in an S3 multipart upload
+pub const MAX_PARTS: usize = 10_000;
+
+/// A part that has been uploaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedPart {
+    /// 1-based part number
+    pub part_number: u32,
+    /// ETag returned by the storage for the part
+    pub etag: String,
+}
+
+/// Byte range of a part before it is uploaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartRange {
+    /// 1-based part number
+    pub part_number: u32,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl PartRange {
+    /// Size of the part in bytes
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Split `total` bytes into parts of `part_size` bytes (the last part may be smaller)
+pub fn part_ranges(total: usize, part_size: usize) -> Vec<PartRange> {
+    let part_size = part_size.max(1);
+    if total == 0 {
+        return vec![PartRange { part_number: 1, start: 0, end: 0 }];
+    }
+
+    (0..total)
+        .step_by(part_size)
+        .enumerate()
+        .map(|(index, start)| PartRange {
+            part_number: index as u32 + 1,
+            start,
+            end: (start + part_size).min(total),
+        })
+        .collect()
+}
+
+type PartUrlFn = Rc<dyn Fn(u32) -> LocalBoxFuture<'static, Result<String, HttpError>>>;
+type CompleteFn = Rc<dyn Fn(Vec<CompletedPart>) -> LocalBoxFuture<'static, Result<(), HttpError>>>;
+
+/// Data to upload, shared with tus uploads
+#[derive(Clone)]
+pub(crate) enum UploadSource {
+    Bytes(Rc<Vec<u8>>),
+    Blob(web_sys::Blob),
+}
+
+impl UploadSource {
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            UploadSource::Bytes(data) => data.len(),
+            UploadSource::Blob(blob) => blob.size() as usize,
+        }
+    }
+
+    /// Read the bytes in `start..end`; blobs are sliced so the whole file is never loaded at once
+    pub(crate) async fn read(&self, start: usize, end: usize) -> Result<Vec<u8>, HttpError> {
+        match self {
+            UploadSource::Bytes(data) => Ok(data[start..end].to_vec()),
+            UploadSource::Blob(blob) => {
+                let read_error = |_| HttpError::Configuration {
+                    message: format!("Failed to read bytes {}..{} from blob", start, end),
+                };
+
+                let slice = blob
+                    .slice_with_f64_and_f64(start as f64, end as f64)
+                    .map_err(read_error)?;
+                let buffer = JsFuture::from(slice.array_buffer()).await.map_err(read_error)?;
+                Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+            }
+        }
+    }
+}
+
+/// Multipart upload to object storage through presigned part URLs
+#[derive(Clone)]
+pub struct PresignedUpload {
+    client: HttpClient,
+    source: UploadSource,
+    part_size: usize,
+    concurrency: usize,
+    part_retries: u32,
+    retry_delay_ms: u32,
+    part_url: Option<PartUrlFn>,
+    complete: Option<CompleteFn>,
+}
+
+impl PresignedUpload {
+    /// Prepare an upload of in-memory data
+    pub fn new(client: &HttpClient, data: Vec<u8>) -> Self {
+        Self::with_source(client, UploadSource::Bytes(Rc::new(data)))
+    }
+
+    /// Prepare an upload of a `Blob` or `File`, read part by part
+    pub fn from_blob(client: &HttpClient, blob: web_sys::Blob) -> Self {
+        Self::with_source(client, UploadSource::Blob(blob))
+    }
+
+    fn with_source(client: &HttpClient, source: UploadSource) -> Self {
+        Self {
+            client: client.clone(),
+            source,
+            part_size: DEFAULT_PART_SIZE,
+            concurrency: 4,
+            part_retries: 3,
+            retry_delay_ms: 1000,
+            part_url: None,
+            complete: None,
+        }
+    }
+
+    /// Set the size of each part in bytes
+    pub fn part_size(mut self, bytes: usize) -> Self {
+        self.part_size = bytes.max(1);
+        self
+    }
+
+    /// Set how many parts are uploaded at the same time
+    pub fn concurrency(mut self, parts: usize) -> Self {
+        self.concurrency = parts.max(1);
+        self
+    }
+
+    /// Set how often a failed part is retried and the delay before the first retry
+    ///
+    /// The delay doubles after each failed attempt of the same part.
+    pub fn part_retry(mut self, count: u32, delay_ms: u32) -> Self {
+        self.part_retries = count;
+        self.retry_delay_ms = delay_ms;
+        self
+    }
+
+    /// Set the callback that returns the presigned URL for a part number
+    pub fn part_url<F, Fut>(mut self, part_url: F) -> Self
+    where
+        F: Fn(u32) -> Fut + 'static,
+        Fut: Future<Output = Result<String, HttpError>> + 'static,
+    {
+        self.part_url = Some(Rc::new(move |part_number| part_url(part_number).boxed_local()));
+        self
+    }
+
+    /// Set the callback that completes the upload with the collected ETags
+    pub fn on_complete<F, Fut>(mut self, complete: F) -> Self
+    where
+        F: Fn(Vec<CompletedPart>) -> Fut + 'static,
+        Fut: Future<Output = Result<(), HttpError>> + 'static,
+    {
+        self.complete = Some(Rc::new(move |parts| complete(parts).boxed_local()));
+        self
+    }
+
+    /// Byte ranges of the parts that will be uploaded
+    pub fn parts(&self) -> Vec<PartRange> {
+        part_ranges(self.source.len(), self.part_size)
+    }
+
+    /// Upload all parts and complete the upload
+    ///
+    /// Returns the completed parts ordered by part number. Only this final
+    /// outcome reaches the client's error handler, with the call name
+    /// `presigned_upload`; part attempts that are retried are not reported.
+    pub async fn start(self) -> Result<Vec<CompletedPart>, HttpError> {
+        if let Some(ref dispatch) = self.client.dispatch {
+            dispatch.emit(AppAction::EnableLoader);
+        }
+
+        let result = self.run().await;
+
+        if let Some(ref dispatch) = self.client.dispatch {
+            dispatch.emit(AppAction::DisableLoader);
+        }
+
+        self.client
+            .builder_for_url(HttpMethod::Put, "")
+            .call_name("presigned_upload")
+            .report_outcome(&result);
+        result
+    }
+
+    async fn run(&self) -> Result<Vec<CompletedPart>, HttpError> {
+        let part_url = self.part_url.clone().ok_or_else(|| HttpError::Configuration {
+            message: "Presigned upload requires a part_url callback".to_string(),
+        })?;
+
+        let ranges = self.parts();
+        if ranges.len() > MAX_PARTS {
+            return Err(HttpError::Configuration {
+                message: format!(
+                    "Upload needs {} parts but at most {} are allowed; increase the part size",
+                    ranges.len(),
+                    MAX_PARTS
+                ),
+            });
+        }
+
+        let completed = self.upload_parts(ranges, |range| self.upload_part(&part_url, range)).await?;
+
+        if let Some(ref complete) = self.complete {
+            complete(completed.clone()).await?;
+        }
+
+        Ok(completed)
+    }
+
+    /// Upload `ranges` with `upload`, running up to `concurrency` parts at a
+    /// time and retrying each part on its own
+    ///
+    /// Returns the completed parts ordered by part number.
+    pub(crate) async fn upload_parts<F, Fut>(&self, ranges: Vec<PartRange>, upload: F) -> Result<Vec<CompletedPart>, HttpError>
+    where
+        F: Fn(PartRange) -> Fut,
+        Fut: Future<Output = Result<CompletedPart, HttpError>>,
+    {
+        let total = self.source.len().max(1);
+        let uploaded = Cell::new(0usize);
+        self.report_progress(0, total);
+
+        let mut completed: Vec<CompletedPart> = futures::stream::iter(ranges)
+            .map(|range| {
+                let upload = &upload;
+                let uploaded = &uploaded;
+                async move {
+                    let part = self.upload_part_with_retries(upload, range).await?;
+                    uploaded.set(uploaded.get() + range.len());
+                    self.report_progress(uploaded.get(), total);
+                    Ok::<_, HttpError>(part)
+                }
+            })
+            .buffer_unordered(self.concurrency)
+            .try_collect()
+            .await?;
+
+        completed.sort_by_key(|part| part.part_number);
+        Ok(completed)
+    }
+
+    async fn upload_part_with_retries<F, Fut>(&self, upload: &F, range: PartRange) -> Result<CompletedPart, HttpError>
+    where
+        F: Fn(PartRange) -> Fut,
+        Fut: Future<Output = Result<CompletedPart, HttpError>>,
+    {
+        let mut attempt = 0;
+        let mut delay = self.retry_delay_ms;
+
+        loop {
+            match upload(range).await {
+                Ok(part) => return Ok(part),
+                Err(error) if attempt < self.part_retries && is_retryable(&error) => {
+                    attempt += 1;
+                    if delay > 0 {
+                        gloo_timers::future::TimeoutFuture::new(delay).await;
+                    }
+                    delay = delay.saturating_mul(2);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    async fn upload_part(&self, part_url: &PartUrlFn, range: PartRange) -> Result<CompletedPart, HttpError> {
+        // Presigned URLs may expire, so a fresh one is requested for every attempt
+        let url = part_url(range.part_number).await?;
+        let data = self.source.read(range.start, range.end).await?;
+
+        let response = self.part_client()
+            .put(&url)
+            .binary(data)
+            .retry(0, 0)
+            .send()
+            .await?;
+
+        let etag = response.header("ETag").ok_or(HttpError::InvalidResponse)?;
+        Ok(CompletedPart {
+            part_number: range.part_number,
+            etag: etag.clone(),
+        })
+    }
+
+    /// Client for part uploads
+    ///
+    /// Presigned URLs carry their own authorization, so the base URL and
+    /// default headers are not applied. Failures are reported once by `start`,
+    /// not per attempt through the client's error handler.
+    fn part_client(&self) -> HttpClient {
+        let mut client = self.client.clone();
+        client.base_url = None;
+        client.default_headers.clear();
+        client.dispatch = None;
+        client.error_handler = None;
+        client
+    }
+
+    fn report_progress(&self, uploaded: usize, total: usize) {
+        if let Some(ref dispatch) = self.client.dispatch {
+            dispatch.emit(AppAction::UpdateProgress(uploaded as f64 / total as f64));
+        }
+    }
+}
+
+impl HttpClient {
+    /// Prepare a multipart upload of `data` through presigned part URLs
+    pub fn presigned_upload(&self, data: Vec<u8>) -> PresignedUpload {
+        PresignedUpload::new(self, data)
+    }
+}
+
+/// Whether a failed part upload should be retried
+fn is_retryable(error: &HttpError) -> bool {
+    match error {
+        HttpError::Network { .. } | HttpError::Timeout | HttpError::InvalidResponse => true,
+        // 403 is what storage returns for an expired presigned URL
+        HttpError::Http { status, .. } => *status >= 500 || matches!(*status, 403 | 408 | 429),
+        _ => false,
+    }
+}
@@ -0,0 +1,27 @@
+//! Protocol Buffers request and response bodies
+//!
+//! Messages are encoded with prost and sent as `application/x-protobuf`.
+
+use prost::Message;
+use crate::{HttpError, HttpResponse, RequestBody, RequestBuilder};
+
+/// `Content-Type` of protobuf bodies
+pub const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
+impl RequestBuilder {
+    /// Set Content-Type header to application/x-protobuf and encode body with prost
+    pub fn protobuf<M: Message>(mut self, message: &M) -> Self {
+        self.config.headers.insert("Content-Type".to_string(), PROTOBUF_CONTENT_TYPE.to_string());
+        self.config.body = RequestBody::Binary(message.encode_to_vec());
+        self
+    }
+}
+
+impl HttpResponse {
+    /// Decode the protobuf response body
+    pub fn protobuf<M: Message + Default>(&self) -> Result<M, HttpError> {
+        M::decode(self.bytes.as_slice()).map_err(|e| HttpError::Serialization {
+            message: format!("Failed to decode protobuf: {}", e),
+        })
+    }
+}
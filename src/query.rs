@@ -0,0 +1,264 @@
+//! Query string building with percent-encoding
+//!
+//! Query parameters are collected on the request and encoded when the request
+//! is sent, so the array style can be chosen independently of the order in
+//! which parameters were added. Parameters are appended to any query string
+//! already present in the URL.
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::fmt;
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Serialize;
+use serde_json::Number;
+use crate::HttpError;
+
+/// Characters left unencoded in query keys and values (RFC 3986 unreserved)
+const QUERY_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// How array values are written to the query string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayFormat {
+    /// `a=1&a=2`
+    #[default]
+    Repeat,
+    /// `a[]=1&a[]=2`
+    Brackets,
+    /// `a[0]=1&a[1]=2`
+    Indices,
+    /// `a=1,2`
+    Comma,
+}
+
+/// Value of a single query parameter
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryValue {
+    Single(String),
+    List(Vec<String>),
+}
+
+/// Percent-encode a query key or value
+pub fn encode_component(input: &str) -> String {
+    utf8_percent_encode(input, QUERY_COMPONENT).to_string()
+}
+
+/// Encode parameters as a query string (without the leading `?`)
+pub fn encode_query(params: &[(String, QueryValue)], format: ArrayFormat) -> String {
+    let mut pairs = Vec::new();
+
+    for (key, value) in params {
+        match value {
+            QueryValue::Single(value) => {
+                pairs.push(format!("{}={}", encode_component(key), encode_component(value)));
+            }
+            QueryValue::List(values) => match format {
+                ArrayFormat::Repeat => {
+                    for value in values {
+                        pairs.push(format!("{}={}", encode_component(key), encode_component(value)));
+                    }
+                }
+                ArrayFormat::Brackets => {
+                    let key = encode_component(&format!("{}[]", key));
+                    for value in values {
+                        pairs.push(format!("{}={}", key, encode_component(value)));
+                    }
+                }
+                ArrayFormat::Indices => {
+                    for (index, value) in values.iter().enumerate() {
+                        let key = encode_component(&format!("{}[{}]", key, index));
+                        pairs.push(format!("{}={}", key, encode_component(value)));
+                    }
+                }
+                ArrayFormat::Comma => {
+                    if !values.is_empty() {
+                        let joined: Vec<_> = values.iter().map(|v| encode_component(v)).collect();
+                        pairs.push(format!("{}={}", encode_component(key), joined.join(",")));
+                    }
+                }
+            },
+        }
+    }
+
+    pairs.join("&")
+}
+
+/// Encode parameters as an `application/x-www-form-urlencoded` body
+///
+/// Spaces become `+` and list values are written as repeated keys.
+pub fn encode_form(params: &[(String, QueryValue)]) -> String {
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+
+    for (key, value) in params {
+        match value {
+            QueryValue::Single(value) => {
+                serializer.append_pair(key, value);
+            }
+            QueryValue::List(values) => {
+                for value in values {
+                    serializer.append_pair(key, value);
+                }
+            }
+        }
+    }
+
+    serializer.finish()
+}
+
+/// Append an encoded query string to a URL, merging with any existing query
+pub fn append_query(url: &str, query: &str) -> String {
+    if query.is_empty() {
+        return url.to_string();
+    }
+
+    let (base, fragment) = match url.find('#') {
+        Some(index) => url.split_at(index),
+        None => (url, ""),
+    };
+
+    let separator = match base.find('?') {
+        None => "?",
+        Some(_) if base.ends_with('?') || base.ends_with('&') => "",
+        Some(_) => "&",
+    };
+
+    format!("{}{}{}{}", base, separator, query, fragment)
+}
+
+/// Serialized value whose object entries keep the order they were written in
+///
+/// `serde_json::Value` sorts object keys unless serde_json's `preserve_order`
+/// feature happens to be enabled somewhere in the dependency graph, so query
+/// parameters are collected through this type to follow field declaration
+/// order either way.
+enum OrderedValue {
+    Null,
+    Scalar(String),
+    Array(Vec<OrderedValue>),
+    Object(Vec<(String, OrderedValue)>),
+}
+
+impl<'de> Deserialize<'de> for OrderedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(OrderedValueVisitor)
+    }
+}
+
+struct OrderedValueVisitor;
+
+impl<'de> Visitor<'de> for OrderedValueVisitor {
+    type Value = OrderedValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Scalar(v.to_string()))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Scalar(v.to_string()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Scalar(v.to_string()))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Scalar(Number::from_f64(v).map_or_else(|| v.to_string(), |n| n.to_string())))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Scalar(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Scalar(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<OrderedValue, D::Error> {
+        OrderedValue::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<OrderedValue, A::Error> {
+        let mut items = Vec::new();
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(OrderedValue::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OrderedValue, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(OrderedValue::Object(entries))
+    }
+}
+
+/// Convert a serializable struct or map into query parameters
+///
+/// `None` fields are skipped, sequences become list values and nested maps
+/// are flattened into `parent[child]` keys. Parameters follow the order in
+/// which fields are serialized, i.e. declaration order for structs.
+pub fn to_query_params<T: Serialize>(value: &T) -> Result<Vec<(String, QueryValue)>, HttpError> {
+    let map_err = |e: serde_json::Error| HttpError::Serialization {
+        message: format!("Failed to serialize query parameters: {}", e),
+    };
+    let json = serde_json::to_string(value).map_err(map_err)?;
+    let value: OrderedValue = serde_json::from_str(&json).map_err(map_err)?;
+
+    let OrderedValue::Object(entries) = value else {
+        return Err(HttpError::Serialization {
+            message: "Query parameters must serialize to a struct or map".to_string(),
+        });
+    };
+
+    let mut params = Vec::new();
+    for (key, value) in entries {
+        flatten_value(key, value, &mut params)?;
+    }
+    Ok(params)
+}
+
+fn flatten_value(key: String, value: OrderedValue, params: &mut Vec<(String, QueryValue)>) -> Result<(), HttpError> {
+    match value {
+        OrderedValue::Null => {}
+        OrderedValue::Array(items) => {
+            let values = items
+                .into_iter()
+                .filter(|item| !matches!(item, OrderedValue::Null))
+                .map(|item| scalar_to_string(&key, item))
+                .collect::<Result<Vec<_>, _>>()?;
+            params.push((key, QueryValue::List(values)));
+        }
+        OrderedValue::Object(entries) => {
+            for (child, value) in entries {
+                flatten_value(format!("{}[{}]", key, child), value, params)?;
+            }
+        }
+        OrderedValue::Scalar(value) => params.push((key, QueryValue::Single(value))),
+    }
+    Ok(())
+}
+
+fn scalar_to_string(key: &str, value: OrderedValue) -> Result<String, HttpError> {
+    match value {
+        OrderedValue::Scalar(value) => Ok(value),
+        _ => Err(HttpError::Serialization {
+            message: format!("Query parameter `{}` contains a nested value that cannot be encoded", key),
+        }),
+    }
+}
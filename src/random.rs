@@ -0,0 +1,27 @@
+//! Random identifiers for idempotency keys and similar tokens
+
+/// Fill a buffer with random bytes from the platform's secure source
+pub(crate) fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    // Fall back to a time-seeded generator if no secure source is available
+    if getrandom::getrandom(&mut bytes).is_err() {
+        let mut seed = js_sys::Date::now().to_bits() ^ 0x9E37_79B9_7F4A_7C15;
+        for byte in bytes.iter_mut() {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            *byte = seed as u8;
+        }
+    }
+    bytes
+}
+
+/// Generate a random (version 4) UUID string
+pub(crate) fn uuid_v4() -> String {
+    let mut bytes: [u8; 16] = random_bytes();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
@@ -0,0 +1,175 @@
+//! Client-side token-bucket rate limiting
+//!
+//! A [`RateLimiter`] delays requests instead of letting them fail with
+//! `429 Too Many Requests`. Limits can be set for a whole client or for
+//! individual hosts; each limit refills `requests` tokens every `interval_ms`
+//! and allows up to `burst` requests to go out back to back.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use url::Url;
+
+/// Rate limit expressed as requests per interval with an optional burst
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub interval_ms: u32,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// Allow `requests` per `interval_ms`, with a burst of the same size
+    pub fn new(requests: u32, interval_ms: u32) -> Self {
+        Self {
+            requests: requests.max(1),
+            interval_ms: interval_ms.max(1),
+            burst: requests.max(1),
+        }
+    }
+
+    /// Allow up to `burst` requests to be sent back to back
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+}
+
+/// Token bucket for a single limit
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    last_refill_ms: f64,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit, now_ms: f64) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            last_refill_ms: now_ms,
+        }
+    }
+
+    fn refill(&mut self, now_ms: f64) {
+        let elapsed = (now_ms - self.last_refill_ms).max(0.0);
+        let per_ms = self.limit.requests as f64 / self.limit.interval_ms as f64;
+        self.tokens = (self.tokens + elapsed * per_ms).min(self.limit.burst as f64);
+        self.last_refill_ms = now_ms;
+    }
+
+    /// Take a token and return how long the caller has to wait for it
+    ///
+    /// Tokens may go negative, which queues callers in the order they reserved.
+    fn reserve(&mut self, now_ms: f64) -> f64 {
+        self.refill(now_ms);
+        self.tokens -= 1.0;
+
+        if self.tokens >= 0.0 {
+            0.0
+        } else {
+            let per_ms = self.limit.requests as f64 / self.limit.interval_ms as f64;
+            -self.tokens / per_ms
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct RateLimiterState {
+    client_limit: Option<RateLimit>,
+    host_limits: HashMap<String, RateLimit>,
+    buckets: HashMap<String, TokenBucket>,
+}
+
+/// Token-bucket rate limiter shared by all clones
+///
+/// Changing the limits of a shared limiter gives the changed value its own
+/// copy, so other clones keep their limits and buckets.
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    state: Rc<RefCell<RateLimiterState>>,
+}
+
+/// Bucket key used for the client-wide limit
+const CLIENT_BUCKET: &str = "*";
+
+impl RateLimiter {
+    /// Create a rate limiter without any limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Address of the shared state, the same for every clone
+    pub(crate) fn identity(&self) -> usize {
+        Rc::as_ptr(&self.state) as usize
+    }
+
+    /// Limit all requests that have no host-specific limit
+    pub fn limit(mut self, limit: RateLimit) -> Self {
+        let state = self.state_mut();
+        state.client_limit = Some(limit);
+        state.buckets.remove(CLIENT_BUCKET);
+        self
+    }
+
+    /// Limit requests to a single host (e.g. `api.example.com`); ports are ignored
+    pub fn host_limit(mut self, host: &str, limit: RateLimit) -> Self {
+        let host = host.to_lowercase();
+        let state = self.state_mut();
+        state.buckets.remove(&host);
+        state.host_limits.insert(host, limit);
+        self
+    }
+
+    /// State of this limiter, copied first if other clones share it
+    fn state_mut(&mut self) -> &mut RateLimiterState {
+        Rc::make_mut(&mut self.state).get_mut()
+    }
+
+    /// Reserve a slot for a request to `url` and return the delay before it may be sent
+    pub fn reserve(&self, url: &str, now_ms: f64) -> f64 {
+        let mut state = self.state.borrow_mut();
+        let host = host_of(url);
+
+        let (key, limit) = match state.host_limits.get(&host) {
+            Some(limit) => (host, *limit),
+            None => match state.client_limit {
+                Some(limit) => (CLIENT_BUCKET.to_string(), limit),
+                None => return 0.0,
+            },
+        };
+
+        state.buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(limit, now_ms))
+            .reserve(now_ms)
+    }
+
+    /// Wait until a request to `url` is allowed by the configured limits
+    pub async fn wait(&self, url: &str) {
+        let delay = self.reserve(url, js_sys::Date::now());
+        if delay > 0.0 {
+            gloo_timers::future::TimeoutFuture::new(delay.ceil() as u32).await;
+        }
+    }
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("RateLimiter")
+            .field("client_limit", &state.client_limit)
+            .field("host_limits", &state.host_limits)
+            .finish()
+    }
+}
+
+/// Lowercased host (without port) of an absolute URL, or an empty string for relative URLs
+fn host_of(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
@@ -0,0 +1,143 @@
+//! URL resolution and validation following the WHATWG URL rules
+//!
+//! Request paths are joined onto the client's base URL as paths *below* the
+//! base (so `/users` against `https://api.example.com/v1` gives
+//! `https://api.example.com/v1/users`), while absolute and protocol-relative
+//! URLs replace it. `.` and `..` segments are resolved and a query string on
+//! the base URL is kept in front of the request's own query.
+
+use url::{ParseError, Url};
+use crate::{path, HttpError};
+
+/// Base used to validate relative URLs, which the browser resolves against the page
+const RELATIVE_BASE: &str = "http://relative.invalid/";
+
+fn invalid_url(url: &str, reason: impl ToString) -> HttpError {
+    HttpError::InvalidUrl {
+        url: url.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Whether `url` starts with a scheme such as `https://` or `ws://`
+fn has_scheme(url: &str) -> bool {
+    match url.find("://") {
+        Some(end) => {
+            let scheme = &url[..end];
+            scheme.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+/// Join a request path onto a base URL
+pub fn join_url(base: &str, path: &str) -> Result<String, HttpError> {
+    if has_scheme(path) {
+        let url = Url::parse(path).map_err(|e| invalid_url(path, e))?;
+        return Ok(restore_placeholders(path, url.as_str()));
+    }
+
+    let mut base_url = Url::parse(base).map_err(|e| invalid_url(base, e))?;
+    if base_url.cannot_be_a_base() {
+        return Err(invalid_url(base, "base URL cannot have paths joined onto it"));
+    }
+
+    // Protocol-relative URLs only take the scheme from the base
+    if path.starts_with("//") {
+        let url = base_url.join(path).map_err(|e| invalid_url(path, e))?;
+        return Ok(restore_placeholders(path, url.as_str()));
+    }
+
+    let base_query = base_url.query().map(str::to_string);
+    base_url.set_query(None);
+    base_url.set_fragment(None);
+    if !base_url.path().ends_with('/') {
+        let with_slash = format!("{}/", base_url.path());
+        base_url.set_path(&with_slash);
+    }
+
+    let mut url = base_url
+        .join(path.trim_start_matches('/'))
+        .map_err(|e| invalid_url(path, e))?;
+
+    if let Some(base_query) = base_query.filter(|q| !q.is_empty()) {
+        let query = match url.query() {
+            Some(query) if !query.is_empty() => format!("{}&{}", base_query, query),
+            _ => base_query,
+        };
+        url.set_query(Some(&query));
+    }
+
+    Ok(restore_placeholders(path, url.as_str()))
+}
+
+/// Undo the percent-encoding of the template's `{name}` placeholders
+///
+/// Only the placeholders of `path` are restored, in order; other encoded
+/// braces in path segments or query values are left alone.
+fn restore_placeholders(path: &str, url: &str) -> String {
+    let mut restored = String::with_capacity(url.len());
+    let mut rest = url;
+
+    for name in path::placeholder_names(path) {
+        let encoded = format!("%7B{}%7D", name);
+        if let Some(index) = rest.find(&encoded) {
+            restored.push_str(&rest[..index]);
+            restored.push('{');
+            restored.push_str(name);
+            restored.push('}');
+            rest = &rest[index + encoded.len()..];
+        }
+    }
+
+    restored.push_str(rest);
+    restored
+}
+
+/// Validate a request URL and check it against an allowlist of origins
+///
+/// Relative URLs are resolved by the browser against the page and are
+/// treated as same-origin. Protocol-relative URLs (`//host/path`, including
+/// backslash variants the browser normalizes) point at another host whose
+/// scheme depends on the page, so they are rejected when an allowlist is set.
+/// An empty allowlist allows every origin.
+pub fn validate_url(url: &str, allowed_origins: &[String]) -> Result<(), HttpError> {
+    if url.trim().is_empty() {
+        return Err(invalid_url(url, "URL is empty"));
+    }
+
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(ParseError::RelativeUrlWithoutBase) => {
+            let base = Url::parse(RELATIVE_BASE).expect("valid relative base");
+            let resolved = base.join(url).map_err(|e| invalid_url(url, e))?;
+            if !allowed_origins.is_empty() && resolved.host_str() != base.host_str() {
+                return Err(invalid_url(url, "protocol-relative URL cannot be checked against allowed origins"));
+            }
+            return Ok(());
+        }
+        Err(e) => return Err(invalid_url(url, e)),
+    };
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(invalid_url(url, format!("unsupported scheme `{}`", parsed.scheme())));
+    }
+
+    if !allowed_origins.is_empty() {
+        let origin = parsed.origin().ascii_serialization();
+        if !allowed_origins.contains(&origin) {
+            return Err(invalid_url(url, format!("origin {} is not allowed", origin)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Normalize an origin such as `https://API.example.com:443/` to `https://api.example.com`
+pub fn normalize_origin(origin: &str) -> String {
+    match Url::parse(origin) {
+        Ok(url) => url.origin().ascii_serialization(),
+        Err(_) => origin.trim_end_matches('/').to_lowercase(),
+    }
+}
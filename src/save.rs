@@ -0,0 +1,236 @@
+//! Browser "save as" downloads
+//!
+//! Responses fetched through the client (for example with an `Authorization`
+//! header, which a plain link cannot send) are saved by wrapping the bytes in
+//! a `Blob`, clicking a temporary `<a download>` element pointing at an object
+//! URL and revoking that URL afterwards. The filename comes from the
+//! `Content-Disposition` header, including RFC 5987 `filename*` values.
+
+use percent_encoding::percent_decode_str;
+use wasm_bindgen::JsCast;
+use crate::{HttpClient, HttpError, HttpResponse, RequestBuilder};
+
+/// Filename used when neither the caller, the headers nor the URL provide one
+const DEFAULT_FILENAME: &str = "download";
+
+/// Delay before the object URL is revoked, giving the browser time to start the download
+const REVOKE_DELAY_MS: u32 = 1000;
+
+/// Extract the filename from a `Content-Disposition` header value
+///
+/// An RFC 5987 `filename*` parameter takes precedence over `filename`.
+/// Directory components are stripped from the result.
+pub fn content_disposition_filename(header: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+
+    for (name, value) in disposition_params(header) {
+        match name.to_ascii_lowercase().as_str() {
+            "filename*" => extended = extended.or_else(|| decode_ext_value(&value)),
+            "filename" => plain = plain.or(Some(value)),
+            _ => {}
+        }
+    }
+
+    extended.or(plain).map(|name| sanitize_filename(&name)).filter(|name| !name.is_empty())
+}
+
+/// Split the parameters of a `Content-Disposition` value into `(name, value)` pairs
+fn disposition_params(header: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut chars = header.chars().peekable();
+
+    // Skip the disposition type
+    for c in chars.by_ref() {
+        if c == ';' {
+            break;
+        }
+    }
+
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace() || *c == ';') {
+            chars.next();
+        }
+
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == '=' || c == ';' {
+                break;
+            }
+            name.push(c);
+            chars.next();
+        }
+        if name.is_empty() && chars.peek().is_none() {
+            break;
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => value.extend(chars.next()),
+                        '"' => break,
+                        c => value.push(c),
+                    }
+                }
+                // Ignore anything between the closing quote and the next parameter
+                for c in chars.by_ref() {
+                    if c == ';' {
+                        break;
+                    }
+                }
+            } else {
+                for c in chars.by_ref() {
+                    if c == ';' {
+                        break;
+                    }
+                    value.push(c);
+                }
+                value = value.trim_end().to_string();
+            }
+        }
+
+        params.push((name.trim().to_string(), value));
+    }
+
+    params
+}
+
+/// Decode an RFC 5987 value such as `UTF-8''na%C3%AFve.txt`
+fn decode_ext_value(value: &str) -> Option<String> {
+    let (charset, rest) = value.split_once('\'')?;
+    let (_language, encoded) = rest.split_once('\'')?;
+    let decoded = percent_decode_str(encoded);
+
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" => decoded.decode_utf8().ok().map(|name| name.into_owned()),
+        "iso-8859-1" => Some(decoded.map(char::from).collect()),
+        _ => None,
+    }
+}
+
+/// Keep only the final path component and drop control characters
+fn sanitize_filename(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    name.chars().filter(|c| !c.is_control()).collect::<String>().trim().to_string()
+}
+
+/// Last path segment of a URL, used when no other filename is available
+fn url_filename(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next()?;
+    let segment = path.rsplit('/').next()?;
+    let decoded = percent_decode_str(segment).decode_utf8_lossy();
+    Some(sanitize_filename(&decoded)).filter(|name| !name.is_empty())
+}
+
+/// Object URL that is revoked when dropped, so early returns do not leak it
+struct ObjectUrl(Option<String>);
+
+impl ObjectUrl {
+    fn url(&self) -> &str {
+        self.0.as_deref().unwrap_or_default()
+    }
+
+    /// Revoke after [`REVOKE_DELAY_MS`] instead, once a download has started
+    fn revoke_later(mut self) {
+        if let Some(url) = self.0.take() {
+            gloo_timers::callback::Timeout::new(REVOKE_DELAY_MS, move || {
+                let _ = web_sys::Url::revoke_object_url(&url);
+            })
+            .forget();
+        }
+    }
+}
+
+impl Drop for ObjectUrl {
+    fn drop(&mut self) {
+        if let Some(ref url) = self.0 {
+            let _ = web_sys::Url::revoke_object_url(url);
+        }
+    }
+}
+
+/// Save bytes as a file through the browser's download mechanism
+pub fn save_bytes(data: &[u8], filename: &str, content_type: &str) -> Result<(), HttpError> {
+    let error = |message: &str| HttpError::Configuration { message: message.to_string() };
+
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(content_type);
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
+        &js_sys::Array::of1(&js_sys::Uint8Array::from(data)),
+        &options,
+    )
+    .map_err(|_| error("Failed to create blob"))?;
+
+    let object_url = web_sys::Url::create_object_url_with_blob(&blob)
+        .map_err(|_| error("Failed to create object URL"))?;
+    let object_url = ObjectUrl(Some(object_url));
+
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| error("No document available"))?;
+    let anchor: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(|_| error("Failed to create anchor element"))?
+        .unchecked_into();
+    anchor.set_href(object_url.url());
+    anchor.set_download(filename);
+
+    // Firefox only follows clicks on anchors that are part of the document
+    let body = document.body().ok_or_else(|| error("No document body available"))?;
+    body.append_child(&anchor).map_err(|_| error("Failed to attach anchor element"))?;
+    anchor.click();
+    anchor.remove();
+
+    object_url.revoke_later();
+    Ok(())
+}
+
+impl HttpResponse {
+    /// Filename from the `Content-Disposition` header
+    pub fn filename(&self) -> Option<String> {
+        self.header("Content-Disposition").and_then(|value| content_disposition_filename(value))
+    }
+
+    /// Name to save the response under: `filename`, the `Content-Disposition` name or the URL's last segment
+    pub fn save_filename(&self, filename: Option<&str>) -> String {
+        filename
+            .map(sanitize_filename)
+            .filter(|name| !name.is_empty())
+            .or_else(|| self.filename())
+            .or_else(|| url_filename(&self.url))
+            .unwrap_or_else(|| DEFAULT_FILENAME.to_string())
+    }
+
+    /// Save the response body as a file in the browser
+    pub fn save(&self, filename: Option<&str>) -> Result<(), HttpError> {
+        let content_type = self
+            .header("Content-Type")
+            .map(String::as_str)
+            .unwrap_or("application/octet-stream");
+        save_bytes(&self.bytes, &self.save_filename(filename), content_type)
+    }
+}
+
+impl RequestBuilder {
+    /// Send the request and save the response body as a file in the browser
+    pub async fn save_as(self, filename: Option<&str>) -> Result<HttpResponse, HttpError> {
+        let response = self.send().await?;
+        response.save(filename)?;
+        Ok(response)
+    }
+}
+
+impl HttpClient {
+    /// Download `url` and save it as a file in the browser
+    pub async fn save_file(&self, url: &str, filename: Option<&str>) -> Result<HttpResponse, HttpError> {
+        self.get(url).save_as(filename).await
+    }
+}
@@ -0,0 +1,193 @@
+//! Client-side concurrency limiting with a priority queue
+//!
+//! A [`RequestScheduler`] caps the number of requests in flight. Requests that
+//! exceed the limit wait in a queue ordered by [`Priority`] (and by arrival
+//! within the same priority), so user-triggered calls can jump ahead of
+//! background work such as prefetches or bulk operations.
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::rc::Rc;
+use futures::channel::oneshot;
+
+/// Request priority used when waiting for a free slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Request waiting for a free slot
+struct Waiting {
+    priority: Priority,
+    sequence: u64,
+    sender: oneshot::Sender<()>,
+}
+
+impl PartialEq for Waiting {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.sequence == other.sequence
+    }
+}
+
+impl Eq for Waiting {}
+
+impl PartialOrd for Waiting {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiting {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Higher priority first, then first come first served
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+struct SchedulerState {
+    max_in_flight: usize,
+    in_flight: usize,
+    waiting: BinaryHeap<Waiting>,
+    next_sequence: u64,
+}
+
+/// Limits the number of concurrent requests, queueing the rest by priority
+///
+/// Clones share the same limit, so one scheduler can be attached to several
+/// clients to bound their combined concurrency.
+#[derive(Clone)]
+pub struct RequestScheduler {
+    state: Rc<RefCell<SchedulerState>>,
+}
+
+impl RequestScheduler {
+    /// Create a scheduler allowing at most `max_in_flight` concurrent requests
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(SchedulerState {
+                max_in_flight: max_in_flight.max(1),
+                in_flight: 0,
+                waiting: BinaryHeap::new(),
+                next_sequence: 0,
+            })),
+        }
+    }
+
+    /// Address of the shared state, the same for every clone
+    pub(crate) fn identity(&self) -> usize {
+        Rc::as_ptr(&self.state) as usize
+    }
+
+    /// Maximum number of concurrent requests
+    pub fn max_in_flight(&self) -> usize {
+        self.state.borrow().max_in_flight
+    }
+
+    /// Number of requests currently holding a slot
+    pub fn in_flight(&self) -> usize {
+        self.state.borrow().in_flight
+    }
+
+    /// Number of requests waiting for a slot
+    pub fn queued(&self) -> usize {
+        self.state.borrow().waiting.len()
+    }
+
+    /// Wait for a free slot; the slot is released when the permit is dropped
+    pub async fn acquire(&self, priority: Priority) -> SchedulerPermit {
+        let (receiver, sequence) = {
+            let mut state = self.state.borrow_mut();
+            if state.in_flight < state.max_in_flight && state.waiting.is_empty() {
+                state.in_flight += 1;
+                return SchedulerPermit { scheduler: self.clone() };
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            let sequence = state.next_sequence;
+            state.next_sequence += 1;
+            state.waiting.push(Waiting { priority, sequence, sender });
+            (receiver, sequence)
+        };
+
+        let mut waiter = QueuedWaiter {
+            receiver,
+            sequence,
+            scheduler: self.clone(),
+            granted: false,
+        };
+
+        // The slot is handed over by `release`, so `in_flight` already counts us
+        let _ = (&mut waiter.receiver).await;
+        waiter.granted = true;
+
+        SchedulerPermit { scheduler: self.clone() }
+    }
+
+    /// Release a slot and hand it to the highest priority waiter, if any
+    fn release(&self) {
+        let mut state = self.state.borrow_mut();
+        state.in_flight = state.in_flight.saturating_sub(1);
+
+        while state.in_flight < state.max_in_flight {
+            let Some(next) = state.waiting.pop() else { break };
+            // Waiters that gave up have dropped their receiver
+            if next.sender.send(()).is_ok() {
+                state.in_flight += 1;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for RequestScheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.borrow();
+        f.debug_struct("RequestScheduler")
+            .field("max_in_flight", &state.max_in_flight)
+            .field("in_flight", &state.in_flight)
+            .field("queued", &state.waiting.len())
+            .finish()
+    }
+}
+
+/// Slot held by an in-flight request
+#[derive(Debug)]
+pub struct SchedulerPermit {
+    scheduler: RequestScheduler,
+}
+
+impl Drop for SchedulerPermit {
+    fn drop(&mut self) {
+        self.scheduler.release();
+    }
+}
+
+/// Queue entry guard for a waiter that went away: leaves the queue, or
+/// returns the slot if it had already been granted
+struct QueuedWaiter {
+    receiver: oneshot::Receiver<()>,
+    sequence: u64,
+    scheduler: RequestScheduler,
+    granted: bool,
+}
+
+impl Drop for QueuedWaiter {
+    fn drop(&mut self) {
+        if self.granted {
+            return;
+        }
+        match self.receiver.try_recv() {
+            Ok(Some(())) => self.scheduler.release(),
+            _ => {
+                let sequence = self.sequence;
+                self.scheduler.state.borrow_mut().waiting.retain(|waiting| waiting.sequence != sequence);
+            }
+        }
+    }
+}
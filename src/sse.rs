@@ -0,0 +1,710 @@
+//! Server-Sent Events client
+//!
+//! SSE connections are created from an [`HttpClient`] so the base URL and
+//! default headers apply. The browser's `EventSource` cannot send custom
+//! headers, so connections that need them are made with `fetch` and the
+//! response body is parsed incrementally with [`SseParser`]. Both transports
+//! reconnect after the connection drops, sending the last received event ID
+//! in the `Last-Event-ID` header, and deliver events as a [`Stream`].
+
+use std::cell::{Cell, RefCell};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::stream::{LocalBoxStream, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use yew::prelude::*;
+use crate::body_stream::BodyReader;
+use crate::{resolve, HttpClient, HttpError, RequestBuilder};
+
+/// Reconnection delay used until the server sends a `retry` field
+const DEFAULT_RETRY_MS: u32 = 3000;
+
+/// Headers `EventSource` sends by itself
+const EVENT_SOURCE_HEADERS: [&str; 3] = ["accept", "cache-control", "last-event-id"];
+
+/// A dispatched server-sent event
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    /// Event type, `message` unless the server sent an `event` field
+    pub event: String,
+    /// Data lines joined with `\n`
+    pub data: String,
+    /// Last event ID at the time the event was dispatched
+    pub id: Option<String>,
+}
+
+impl SseEvent {
+    /// Parse the event data as JSON
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        serde_json::from_str(&self.data).map_err(|e| HttpError::Serialization {
+            message: format!("Failed to deserialize {} event: {}", self.event, e),
+        })
+    }
+}
+
+/// Incremental parser for the `text/event-stream` format
+///
+/// Input may be split at arbitrary byte positions, including inside UTF-8
+/// sequences and between the `\r` and `\n` of a line ending.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    line: Vec<u8>,
+    after_cr: bool,
+    started: bool,
+    event: Option<String>,
+    data: String,
+    last_event_id: Option<String>,
+    retry_ms: Option<u32>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parser that resumes after the given event ID
+    pub fn with_last_event_id(last_event_id: Option<String>) -> Self {
+        Self {
+            last_event_id,
+            ..Self::default()
+        }
+    }
+
+    /// Feed a chunk of the stream, returning the events it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+
+        for &byte in chunk {
+            let after_cr = std::mem::replace(&mut self.after_cr, false);
+            match byte {
+                b'\n' if after_cr => {}
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    if let Some(event) = self.process_line(&line) {
+                        events.push(event);
+                    }
+                }
+                _ => self.line.push(byte),
+            }
+        }
+
+        events
+    }
+
+    /// Discard a partially received event, e.g. when the connection drops
+    pub fn reset(&mut self) {
+        self.line.clear();
+        self.after_cr = false;
+        self.started = false;
+        self.event = None;
+        self.data.clear();
+    }
+
+    /// ID of the last event, sent as `Last-Event-ID` when reconnecting
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// Reconnection delay requested by the server
+    pub fn retry_ms(&self) -> Option<u32> {
+        self.retry_ms
+    }
+
+    fn process_line(&mut self, line: &[u8]) -> Option<SseEvent> {
+        let mut line = String::from_utf8_lossy(line).into_owned();
+        if !self.started {
+            self.started = true;
+            if let Some(stripped) = line.strip_prefix('\u{feff}') {
+                line = stripped.to_string();
+            }
+        }
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_str(), ""),
+        };
+
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => {
+                self.last_event_id = Some(value.to_string()).filter(|id| !id.is_empty());
+            }
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry_ms = value.parse().ok();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+        Some(SseEvent {
+            event: event.filter(|name| !name.is_empty()).unwrap_or_else(|| "message".to_string()),
+            data,
+            id: self.last_event_id.clone(),
+        })
+    }
+}
+
+/// How the connection is made
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SseTransport {
+    /// `EventSource` unless custom headers are set, `fetch` otherwise
+    #[default]
+    Auto,
+    /// Browser `EventSource`; custom headers are not sent
+    EventSource,
+    /// `fetch` with a streamed response body
+    Fetch,
+}
+
+/// State of an SSE connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SseState {
+    Connecting,
+    Open,
+    Closed,
+}
+
+/// A browser `EventSource` and the listeners keeping its callbacks alive
+struct EventSourceHandle {
+    source: web_sys::EventSource,
+    _listeners: Vec<Closure<dyn FnMut(web_sys::Event)>>,
+}
+
+struct SseShared {
+    closed: Cell<bool>,
+    state: Cell<SseState>,
+    failures: Cell<u32>,
+    last_event_id: RefCell<Option<String>>,
+    retry_ms: Cell<u32>,
+    abort: RefCell<Option<web_sys::AbortController>>,
+    source: RefCell<Option<EventSourceHandle>>,
+    on_state_change: Option<Callback<SseState>>,
+}
+
+impl SseShared {
+    /// Close the EventSource and drop its listeners
+    ///
+    /// The listeners hold the stream's sender and this state, so dropping
+    /// them ends the stream and breaks the reference cycle. The drop is
+    /// deferred since this may run inside one of those listeners.
+    fn close_source(&self) {
+        if let Some(handle) = self.source.borrow_mut().take() {
+            handle.source.close();
+            wasm_bindgen_futures::spawn_local(async move { drop(handle) });
+        }
+    }
+
+    fn set_state(&self, state: SseState) {
+        if self.state.replace(state) != state {
+            if let Some(ref handler) = self.on_state_change {
+                handler.emit(state);
+            }
+        }
+    }
+}
+
+/// Handle to an SSE connection, shared by all clones
+#[derive(Clone)]
+pub struct SseConnection {
+    shared: Rc<SseShared>,
+}
+
+impl SseConnection {
+    /// Close the connection and stop reconnecting
+    pub fn close(&self) {
+        self.shared.closed.set(true);
+        if let Some(controller) = self.shared.abort.borrow_mut().take() {
+            controller.abort();
+        }
+        self.shared.close_source();
+        self.shared.set_state(SseState::Closed);
+    }
+
+    /// Current state of the connection
+    pub fn state(&self) -> SseState {
+        self.shared.state.get()
+    }
+
+    /// ID of the last received event
+    pub fn last_event_id(&self) -> Option<String> {
+        self.shared.last_event_id.borrow().clone()
+    }
+}
+
+/// Stream of server-sent events; dropping it closes the connection
+pub struct SseStream {
+    receiver: UnboundedReceiver<Result<SseEvent, HttpError>>,
+    connection: SseConnection,
+}
+
+impl SseStream {
+    /// Handle to close the connection or inspect its state
+    pub fn connection(&self) -> SseConnection {
+        self.connection.clone()
+    }
+
+    /// Decode the data of every event as JSON
+    pub fn json<T: DeserializeOwned + 'static>(self) -> LocalBoxStream<'static, Result<T, HttpError>> {
+        self.map(|event| event.and_then(|event| event.json())).boxed_local()
+    }
+}
+
+impl Stream for SseStream {
+    type Item = Result<SseEvent, HttpError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for SseStream {
+    fn drop(&mut self) {
+        self.connection.close();
+    }
+}
+
+/// Builder for an SSE connection
+pub struct SseBuilder {
+    request: RequestBuilder,
+    transport: SseTransport,
+    event_types: Vec<String>,
+    retry_ms: u32,
+    max_retries: Option<u32>,
+    last_event_id: Option<String>,
+    on_state_change: Option<Callback<SseState>>,
+}
+
+impl SseBuilder {
+    fn new(request: RequestBuilder) -> Self {
+        Self {
+            request: request.header("Accept", "text/event-stream"),
+            transport: SseTransport::Auto,
+            event_types: Vec::new(),
+            retry_ms: DEFAULT_RETRY_MS,
+            max_retries: None,
+            last_event_id: None,
+            on_state_change: None,
+        }
+    }
+
+    /// Add a header; forces the `fetch` transport in `Auto` mode
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.request = self.request.header(name, value);
+        self
+    }
+
+    /// Add a percent-encoded query parameter
+    pub fn query(mut self, key: &str, value: impl ToString) -> Self {
+        self.request = self.request.query(key, value);
+        self
+    }
+
+    /// Choose how the connection is made
+    pub fn transport(mut self, transport: SseTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Listen for a named event type
+    ///
+    /// `EventSource` only delivers `message` events and the types registered
+    /// here; the `fetch` transport delivers every event.
+    pub fn listen(mut self, event_type: &str) -> Self {
+        self.event_types.push(event_type.to_string());
+        self
+    }
+
+    /// Delay before reconnecting until the server sends a `retry` field
+    pub fn retry_delay(mut self, ms: u32) -> Self {
+        self.retry_ms = ms;
+        self
+    }
+
+    /// Give up after `count` consecutive failed connection attempts
+    pub fn max_retries(mut self, count: u32) -> Self {
+        self.max_retries = Some(count);
+        self
+    }
+
+    /// Resume after the given event ID
+    pub fn last_event_id(mut self, id: &str) -> Self {
+        self.last_event_id = Some(id.to_string());
+        self
+    }
+
+    /// Set a handler called whenever the connection state changes
+    pub fn on_state_change<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(SseState) + 'static,
+    {
+        self.on_state_change = Some(Callback::from(handler));
+        self
+    }
+
+    /// Transport used for this connection after resolving `Auto`
+    pub fn resolved_transport(&self) -> SseTransport {
+        match self.transport {
+            SseTransport::Auto => {
+                let custom_headers = self.request.config.headers
+                    .keys()
+                    .any(|name| !EVENT_SOURCE_HEADERS.contains(&name.to_ascii_lowercase().as_str()));
+                if custom_headers {
+                    SseTransport::Fetch
+                } else {
+                    SseTransport::EventSource
+                }
+            }
+            transport => transport,
+        }
+    }
+
+    /// Open the connection
+    pub fn connect(self) -> SseStream {
+        let (sender, receiver) = mpsc::unbounded();
+        let shared = Rc::new(SseShared {
+            closed: Cell::new(false),
+            state: Cell::new(SseState::Connecting),
+            failures: Cell::new(0),
+            last_event_id: RefCell::new(self.last_event_id.clone()),
+            retry_ms: Cell::new(self.retry_ms),
+            abort: RefCell::new(None),
+            source: RefCell::new(None),
+            on_state_change: self.on_state_change.clone(),
+        });
+
+        match self.resolved_transport() {
+            SseTransport::Fetch | SseTransport::Auto => {
+                wasm_bindgen_futures::spawn_local(run_fetch(self.request, shared.clone(), sender, self.max_retries));
+            }
+            SseTransport::EventSource => {
+                let url = self.request.config
+                    .request_url()
+                    .and_then(|url| resolve::validate_url(&url, &self.request.config.allowed_origins).map(|_| url));
+                match url {
+                    Ok(url) => {
+                        let config = Rc::new(EventSourceConfig {
+                            url,
+                            event_types: self.event_types,
+                            max_retries: self.max_retries,
+                        });
+                        open_event_source(config, shared.clone(), sender);
+                    }
+                    Err(error) => {
+                        let _ = sender.unbounded_send(Err(error));
+                        shared.set_state(SseState::Closed);
+                    }
+                }
+            }
+        }
+
+        SseStream {
+            receiver,
+            connection: SseConnection { shared },
+        }
+    }
+}
+
+impl HttpClient {
+    /// Prepare a Server-Sent Events connection to `path`
+    pub fn sse(&self, path: &str) -> SseBuilder {
+        SseBuilder::new(self.get(path).no_timeout().retry(0, 0).deduplicate(false))
+    }
+}
+
+/// Errors after which reconnecting cannot help
+fn is_fatal(error: &HttpError) -> bool {
+    match error {
+        HttpError::Network { .. } | HttpError::Timeout => false,
+        HttpError::Http { status, .. } => *status < 500 && !matches!(*status, 408 | 429),
+        _ => true,
+    }
+}
+
+/// Connect with `fetch`, read the stream and reconnect until closed
+async fn run_fetch(
+    request: RequestBuilder,
+    shared: Rc<SseShared>,
+    sender: UnboundedSender<Result<SseEvent, HttpError>>,
+    max_retries: Option<u32>,
+) {
+    let mut parser = SseParser::with_last_event_id(shared.last_event_id.borrow().clone());
+    let is_closed = |shared: &SseShared| shared.closed.get() || sender.is_closed();
+
+    while !is_closed(&shared) {
+        shared.set_state(SseState::Connecting);
+
+        let mut attempt = request.clone().header("Cache-Control", "no-cache");
+        if let Some(id) = parser.last_event_id() {
+            attempt = attempt.header("Last-Event-ID", id);
+        }
+        let controller = web_sys::AbortController::new().ok();
+        *shared.abort.borrow_mut() = controller.clone();
+        let signal = controller.as_ref().map(|controller| controller.signal());
+
+        let opened = attempt.open_stream(signal.as_ref()).await.and_then(|response| {
+            let is_event_stream = response
+                .headers()
+                .get("Content-Type")
+                .is_some_and(|content_type| content_type.starts_with("text/event-stream"));
+            if !is_event_stream {
+                return Err(HttpError::InvalidResponse);
+            }
+            Ok((response.status(), BodyReader::new(&response)?))
+        });
+
+        match opened {
+            // 204 No Content tells the client to stop reconnecting
+            Ok((204, _)) => break,
+            Ok((_, reader)) => {
+                shared.set_state(SseState::Open);
+                shared.failures.set(0);
+
+                while let Ok(Some(chunk)) = reader.next_chunk().await {
+                    for event in parser.feed(&chunk) {
+                        *shared.last_event_id.borrow_mut() = event.id.clone();
+                        if sender.unbounded_send(Ok(event)).is_err() {
+                            break;
+                        }
+                    }
+                    if is_closed(&shared) {
+                        reader.cancel();
+                        break;
+                    }
+                }
+                parser.reset();
+            }
+            Err(error) => {
+                let failures = shared.failures.get() + 1;
+                shared.failures.set(failures);
+                if is_fatal(&error) || max_retries.is_some_and(|max| failures > max) {
+                    let _ = sender.unbounded_send(Err(error));
+                    break;
+                }
+            }
+        }
+
+        if is_closed(&shared) {
+            break;
+        }
+
+        let delay = parser.retry_ms().unwrap_or(shared.retry_ms.get());
+        gloo_timers::future::TimeoutFuture::new(delay).await;
+    }
+
+    shared.abort.borrow_mut().take();
+    shared.set_state(SseState::Closed);
+}
+
+struct EventSourceConfig {
+    url: String,
+    event_types: Vec<String>,
+    max_retries: Option<u32>,
+}
+
+/// Open a browser `EventSource`, reopening it if the browser gives up
+///
+/// The browser reconnects by itself (sending `Last-Event-ID`) after the
+/// connection drops; it only closes the source for fatal errors such as a
+/// non-200 status, in which case a new source is opened after the retry delay.
+fn open_event_source(
+    config: Rc<EventSourceConfig>,
+    shared: Rc<SseShared>,
+    sender: UnboundedSender<Result<SseEvent, HttpError>>,
+) {
+    let source = match web_sys::EventSource::new(&config.url) {
+        Ok(source) => source,
+        Err(e) => {
+            let _ = sender.unbounded_send(Err(HttpError::Network {
+                message: format!("Failed to open EventSource: {:?}", e),
+            }));
+            shared.set_state(SseState::Closed);
+            return;
+        }
+    };
+
+    let mut listeners = Vec::new();
+    let event_types = std::iter::once("message").chain(config.event_types.iter().map(String::as_str));
+    for event_type in event_types {
+        let name = event_type.to_string();
+        let shared = shared.clone();
+        let sender = sender.clone();
+        let listener = Closure::<dyn FnMut(web_sys::Event)>::new(move |event: web_sys::Event| {
+            let event: web_sys::MessageEvent = event.unchecked_into();
+            let id = Some(event.last_event_id()).filter(|id| !id.is_empty());
+            *shared.last_event_id.borrow_mut() = id.clone();
+            let _ = sender.unbounded_send(Ok(SseEvent {
+                event: name.clone(),
+                data: event.data().as_string().unwrap_or_default(),
+                id,
+            }));
+        });
+        let _ = source.add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref());
+        listeners.push(listener);
+    }
+
+    let on_open = {
+        let shared = shared.clone();
+        Closure::<dyn FnMut(web_sys::Event)>::new(move |_| {
+            shared.failures.set(0);
+            shared.set_state(SseState::Open);
+        })
+    };
+    let _ = source.add_event_listener_with_callback("open", on_open.as_ref().unchecked_ref());
+    listeners.push(on_open);
+
+    let on_error = {
+        let shared = shared.clone();
+        let config = config.clone();
+        let source = source.clone();
+        Closure::<dyn FnMut(web_sys::Event)>::new(move |_| {
+            if shared.closed.get() {
+                return;
+            }
+            if source.ready_state() != web_sys::EventSource::CLOSED {
+                // The browser is reconnecting by itself
+                shared.set_state(SseState::Connecting);
+                return;
+            }
+
+            let failures = shared.failures.get() + 1;
+            shared.failures.set(failures);
+            if config.max_retries.is_some_and(|max| failures > max) {
+                let _ = sender.unbounded_send(Err(HttpError::Network {
+                    message: "EventSource connection failed".to_string(),
+                }));
+                shared.closed.set(true);
+                shared.close_source();
+                shared.set_state(SseState::Closed);
+                return;
+            }
+
+            shared.set_state(SseState::Connecting);
+            let shared = shared.clone();
+            let config = config.clone();
+            let sender = sender.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                gloo_timers::future::TimeoutFuture::new(shared.retry_ms.get()).await;
+                if !shared.closed.get() && !sender.is_closed() {
+                    open_event_source(config, shared, sender);
+                }
+            });
+        })
+    };
+    let _ = source.add_event_listener_with_callback("error", on_error.as_ref().unchecked_ref());
+    listeners.push(on_error);
+
+    *shared.source.borrow_mut() = Some(EventSourceHandle {
+        source,
+        _listeners: listeners,
+    });
+}
+
+/// State of a [`use_sse`] subscription
+pub struct UseSseHandle<T> {
+    data: UseStateHandle<Option<Rc<T>>>,
+    error: UseStateHandle<Option<HttpError>>,
+    state: UseStateHandle<SseState>,
+    connection: Rc<RefCell<Option<SseConnection>>>,
+}
+
+impl<T> UseSseHandle<T> {
+    /// Most recently received event, decoded from JSON
+    pub fn data(&self) -> Option<Rc<T>> {
+        (*self.data).clone()
+    }
+
+    /// Last error, e.g. an event that failed to decode
+    pub fn error(&self) -> Option<HttpError> {
+        (*self.error).clone()
+    }
+
+    /// Current connection state
+    pub fn state(&self) -> SseState {
+        *self.state
+    }
+
+    /// Close the connection
+    pub fn close(&self) {
+        if let Some(connection) = self.connection.borrow().as_ref() {
+            connection.close();
+        }
+    }
+}
+
+/// Hook subscribing to JSON-encoded server-sent events from `path`
+///
+/// The connection is opened when the component mounts or `path` changes and
+/// closed when it unmounts.
+#[hook]
+pub fn use_sse<T>(client: &HttpClient, path: &str) -> UseSseHandle<T>
+where
+    T: DeserializeOwned + 'static,
+{
+    let data = use_state(|| None::<Rc<T>>);
+    let error = use_state(|| None::<HttpError>);
+    let state = use_state(|| SseState::Connecting);
+    let connection = use_mut_ref(|| None::<SseConnection>);
+
+    {
+        let client = client.clone();
+        let data = data.clone();
+        let error = error.clone();
+        let state = state.clone();
+        let connection = connection.clone();
+        use_effect_with(path.to_string(), move |path| {
+            let stream = client
+                .sse(path)
+                .on_state_change(move |new_state| state.set(new_state))
+                .connect();
+            *connection.borrow_mut() = Some(stream.connection());
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let mut events = stream.json::<T>();
+                while let Some(event) = events.next().await {
+                    match event {
+                        Ok(value) => data.set(Some(Rc::new(value))),
+                        Err(e) => error.set(Some(e)),
+                    }
+                }
+            });
+
+            move || {
+                if let Some(connection) = connection.borrow_mut().take() {
+                    connection.close();
+                }
+            }
+        });
+    }
+
+    UseSseHandle {
+        data,
+        error,
+        state,
+        connection,
+    }
+}
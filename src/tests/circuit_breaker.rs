@@ -0,0 +1,140 @@
+use crate::{CircuitBreaker, CircuitState, HttpError, HttpResponse};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn server_error() -> Result<HttpResponse, HttpError> {
+    Err(HttpError::Http { status: 503, message: "Service Unavailable".to_string(), body: None })
+}
+
+fn ok_response() -> Result<HttpResponse, HttpError> {
+    Ok(HttpResponse::new(200, "", Vec::new()))
+}
+
+#[test]
+fn test_circuit_opens_after_threshold() {
+    let breaker = CircuitBreaker::new(2, 1000);
+    let url = "https://api.example.com/users";
+    
+    let permit = breaker.check(url, 0.0).unwrap();
+    assert_eq!(permit.key(), "https://api.example.com");
+    permit.record(&server_error(), 0.0);
+    assert_eq!(breaker.state(url), CircuitState::Closed);
+    
+    let permit = breaker.check(url, 10.0).unwrap();
+    permit.record(&server_error(), 10.0);
+    assert_eq!(breaker.state(url), CircuitState::Open);
+    
+    match breaker.check(url, 510.0) {
+        Err(HttpError::CircuitOpen { key, retry_after_ms }) => {
+            assert_eq!(key, "https://api.example.com");
+            assert_eq!(retry_after_ms, 500);
+        }
+        other => panic!("Expected open circuit, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_circuit_half_opens_and_recovers() {
+    let breaker = CircuitBreaker::new(1, 1000);
+    let url = "https://api.example.com/users";
+    
+    let permit = breaker.check(url, 0.0).unwrap();
+    permit.record(&server_error(), 0.0);
+    
+    // After the cooldown a single probe is allowed
+    let probe = breaker.check(url, 1000.0).unwrap();
+    assert_eq!(breaker.state(url), CircuitState::HalfOpen);
+    assert!(matches!(breaker.check(url, 1000.0), Err(HttpError::CircuitOpen { .. })));
+    
+    probe.record(&ok_response(), 1100.0);
+    assert_eq!(breaker.state(url), CircuitState::Closed);
+    assert!(breaker.check(url, 1100.0).is_ok());
+}
+
+#[test]
+fn test_failed_probe_reopens_circuit() {
+    let breaker = CircuitBreaker::new(1, 1000);
+    let url = "https://api.example.com/users";
+    
+    let permit = breaker.check(url, 0.0).unwrap();
+    permit.record(&server_error(), 0.0);
+    
+    let probe = breaker.check(url, 1000.0).unwrap();
+    probe.record(&server_error(), 1000.0);
+    assert_eq!(breaker.state(url), CircuitState::Open);
+    assert!(matches!(breaker.check(url, 1500.0), Err(HttpError::CircuitOpen { .. })));
+}
+
+#[test]
+fn test_client_errors_do_not_trip_circuit() {
+    let breaker = CircuitBreaker::new(1, 1000);
+    let url = "https://api.example.com/users";
+    
+    let permit = breaker.check(url, 0.0).unwrap();
+    let not_found: Result<(), HttpError> = Err(HttpError::Http { status: 404, message: "Not Found".to_string(), body: None });
+    permit.record(&not_found, 0.0);
+    
+    assert_eq!(breaker.state(url), CircuitState::Closed);
+}
+
+#[test]
+fn test_circuits_per_path_prefix_and_notifications() {
+    let changes = Rc::new(RefCell::new(Vec::new()));
+    let breaker = {
+        let changes = changes.clone();
+        CircuitBreaker::new(1, 1000)
+            .path_prefix("/payments")
+            .on_state_change(move |key, state| changes.borrow_mut().push((key.to_string(), state)))
+    };
+    
+    let payments = "https://api.example.com/payments/42?expand=true";
+    let users = "https://api.example.com/users";
+    
+    let permit = breaker.check(payments, 0.0).unwrap();
+    assert_eq!(permit.key(), "https://api.example.com/payments");
+    permit.record(&server_error(), 0.0);
+    
+    assert_eq!(breaker.state(payments), CircuitState::Open);
+    assert_eq!(breaker.state(users), CircuitState::Closed);
+    assert_eq!(
+        *changes.borrow(),
+        vec![("https://api.example.com/payments".to_string(), CircuitState::Open)]
+    );
+}
+
+#[test]
+fn test_dropped_probe_releases_half_open_circuit() {
+    let breaker = CircuitBreaker::new(1, 1000);
+    let url = "https://api.example.com/users";
+    
+    let permit = breaker.check(url, 0.0).unwrap();
+    permit.record(&server_error(), 0.0);
+    
+    let probe = breaker.check(url, 1000.0).unwrap();
+    assert!(probe.is_probe());
+    
+    // Other requests wait for the probe, with a non-zero retry hint
+    match breaker.check(url, 1200.0) {
+        Err(HttpError::CircuitOpen { retry_after_ms, .. }) => assert_eq!(retry_after_ms, 800),
+        other => panic!("Expected open circuit, got {:?}", other),
+    }
+    match breaker.check(url, 5000.0) {
+        Err(HttpError::CircuitOpen { retry_after_ms, .. }) => assert_eq!(retry_after_ms, 1),
+        other => panic!("Expected open circuit, got {:?}", other),
+    }
+    
+    // The probe's request future was cancelled
+    drop(probe);
+    let probe = breaker.check(url, 5000.0).unwrap();
+    assert!(probe.is_probe());
+    probe.record(&ok_response(), 5100.0);
+    assert_eq!(breaker.state(url), CircuitState::Closed);
+}
+
+#[test]
+fn test_circuit_keys_use_url_origin() {
+    let breaker = CircuitBreaker::new(1, 1000);
+    assert_eq!(breaker.key_for("https://API.example.com:443/users?x=1"), "https://api.example.com");
+    assert_eq!(breaker.key_for("http://api.example.com:8080/users"), "http://api.example.com:8080");
+    assert_eq!(breaker.key_for("/users"), "");
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use crate::codec::media_type;
+use crate::{BuiltinCodec, Codec, HttpError, HttpMethod, HttpResponse, JsonCodec, RequestBody, RequestBuilder};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Item {
+    id: u32,
+    name: String,
+}
+
+fn item() -> Item {
+    Item { id: 7, name: "widget".to_string() }
+}
+
+fn response(content_type: Option<&str>, bytes: Vec<u8>) -> HttpResponse {
+    let response = HttpResponse::new(200, "https://api.example.com/items/7", bytes);
+    match content_type {
+        Some(content_type) => response.with_header("content-type", content_type),
+        None => response,
+    }
+}
+
+#[test]
+fn test_codec_media_type() {
+    assert_eq!(media_type("Application/JSON; charset=utf-8"), "application/json");
+    assert_eq!(media_type(" application/cbor "), "application/cbor");
+}
+
+#[test]
+fn test_codec_json_round_trip() {
+    let bytes = JsonCodec.encode(&item()).unwrap();
+    assert_eq!(bytes, br#"{"id":7,"name":"widget"}"#);
+    assert_eq!(JsonCodec.decode::<Item>(&bytes).unwrap(), item());
+
+    assert!(JsonCodec.accepts("application/json"));
+    assert!(JsonCodec.accepts("application/problem+json"));
+    assert!(!JsonCodec.accepts("text/plain"));
+}
+
+#[test]
+fn test_codec_body_with_sets_content_type() {
+    let builder = RequestBuilder::new(HttpMethod::Post, "https://api.example.com/items")
+        .body_with(JsonCodec, &item())
+        .unwrap();
+
+    assert_eq!(builder.config.headers.get("Content-Type").map(String::as_str), Some("application/json"));
+    match builder.config.body {
+        RequestBody::Binary(ref bytes) => assert_eq!(JsonCodec.decode::<Item>(bytes).unwrap(), item()),
+        _ => panic!("Expected binary body"),
+    }
+}
+
+#[test]
+fn test_codec_negotiation_from_content_type() {
+    let json = JsonCodec.encode(&item()).unwrap();
+    assert_eq!(response(Some("application/json; charset=utf-8"), json.clone()).decode::<Item>().unwrap(), item());
+    // Without a Content-Type the codec has to be chosen explicitly
+    assert!(matches!(response(None, json.clone()).decode::<Item>(), Err(HttpError::Serialization { .. })));
+    assert_eq!(response(None, json.clone()).decode_or::<_, Item>(JsonCodec).unwrap(), item());
+    assert_eq!(response(None, json).decode_with::<_, Item>(BuiltinCodec::Json).unwrap(), item());
+
+    let error = response(Some("image/png"), vec![0x89]).decode::<Item>();
+    assert!(matches!(error, Err(HttpError::Serialization { .. })));
+
+    assert_eq!(BuiltinCodec::for_content_type("application/hal+json"), Some(BuiltinCodec::Json));
+    assert_eq!(BuiltinCodec::for_content_type("text/html"), None);
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn test_codec_msgpack() {
+    use crate::MsgPackCodec;
+
+    let bytes = MsgPackCodec.encode(&item()).unwrap();
+    assert_eq!(MsgPackCodec.decode::<Item>(&bytes).unwrap(), item());
+    assert_eq!(response(Some("application/x-msgpack"), bytes).decode::<Item>().unwrap(), item());
+    assert!(BuiltinCodec::accept_header().contains("application/msgpack"));
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_codec_cbor() {
+    use crate::CborCodec;
+
+    let bytes = CborCodec.encode(&item()).unwrap();
+    assert_eq!(CborCodec.decode::<Item>(&bytes).unwrap(), item());
+    assert_eq!(response(Some("application/cbor"), bytes).decode::<Item>().unwrap(), item());
+    assert_eq!(BuiltinCodec::for_content_type("application/cose+cbor"), Some(BuiltinCodec::Cbor));
+
+    let error = CborCodec.decode::<Item>(&[0xff, 0x00]);
+    assert!(matches!(error, Err(HttpError::Serialization { .. })));
+}
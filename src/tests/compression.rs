@@ -0,0 +1,131 @@
+#[cfg(feature = "compression-fallback")]
+use std::io::Read;
+use std::cell::RefCell;
+use std::rc::Rc;
+#[cfg(feature = "compression-fallback")]
+use flate2::read::{GzDecoder, ZlibDecoder};
+use crate::compression::{body_bytes, DEFAULT_MIN_SIZE};
+#[cfg(feature = "compression-fallback")]
+use crate::compression::compress_bytes;
+use crate::{CompressionPolicy, Encoding, HttpClient, HttpMethod, RequestBody, RequestBuilder, RequestConfig};
+
+fn telemetry() -> String {
+    let events: Vec<String> = (0..200).map(|i| format!("{{\"event\":\"click\",\"seq\":{}}}", i)).collect();
+    format!("[{}]", events.join(","))
+}
+
+/// Send `builder` and return the configuration it was sent with
+///
+/// The empty URL fails before any network access, and the error handler
+/// receives the final configuration.
+fn sent_config(builder: impl FnOnce(&HttpClient) -> RequestBuilder) -> RequestConfig {
+    let seen = Rc::new(RefCell::new(None));
+    let client = {
+        let seen = seen.clone();
+        HttpClient::new().on_error(move |_, config| *seen.borrow_mut() = Some(config.clone()))
+    };
+
+    let result = futures::executor::block_on(builder(&client).send());
+    assert!(result.is_err());
+    let config = seen.borrow_mut().take();
+    config.expect("error handler called")
+}
+
+#[cfg(feature = "compression-fallback")]
+#[test]
+fn test_compression_gzip_round_trip() {
+    let data = telemetry();
+    let compressed = compress_bytes(data.as_bytes(), Encoding::Gzip).unwrap();
+    assert!(compressed.len() < data.len());
+    assert_eq!(&compressed[..2], &[0x1f, 0x8b]);
+
+    let mut decompressed = String::new();
+    GzDecoder::new(compressed.as_slice()).read_to_string(&mut decompressed).unwrap();
+    assert_eq!(decompressed, data);
+}
+
+#[cfg(feature = "compression-fallback")]
+#[test]
+fn test_compression_deflate_round_trip() {
+    let data = telemetry();
+    let compressed = compress_bytes(data.as_bytes(), Encoding::Deflate).unwrap();
+
+    let mut decompressed = String::new();
+    ZlibDecoder::new(compressed.as_slice()).read_to_string(&mut decompressed).unwrap();
+    assert_eq!(decompressed, data);
+}
+
+#[test]
+fn test_compression_policy_threshold() {
+    let builder = RequestBuilder::new(HttpMethod::Post, "https://api.example.com/telemetry").compress(Encoding::Gzip);
+    let policy = builder.config.compression.unwrap();
+    assert_eq!(policy, CompressionPolicy { encoding: Encoding::Gzip, min_size: DEFAULT_MIN_SIZE });
+    assert!(!policy.applies_to(DEFAULT_MIN_SIZE - 1));
+    assert!(policy.applies_to(DEFAULT_MIN_SIZE));
+
+    let builder = builder.compress_above(Encoding::Deflate, 0);
+    assert_eq!(builder.config.compression.unwrap().encoding, Encoding::Deflate);
+    assert!(builder.config.compression.unwrap().applies_to(0));
+}
+
+#[test]
+fn test_compression_applies_to_text_json_and_binary_bodies() {
+    assert_eq!(body_bytes(&RequestBody::Json("{}".to_string())), Some(&b"{}"[..]));
+    assert_eq!(body_bytes(&RequestBody::Text("hi".to_string())), Some(&b"hi"[..]));
+    assert_eq!(body_bytes(&RequestBody::Binary(vec![1, 2])), Some(&[1u8, 2][..]));
+    assert_eq!(body_bytes(&RequestBody::UrlEncoded("a=1".to_string())), None);
+    assert_eq!(body_bytes(&RequestBody::None), None);
+
+    assert_eq!(Encoding::Gzip.as_str(), "gzip");
+    assert_eq!(Encoding::Deflate.as_str(), "deflate");
+}
+
+#[cfg(feature = "compression-fallback")]
+#[test]
+fn test_send_replaces_body_and_sets_content_encoding() {
+    let data = telemetry();
+    let config = sent_config(|client| client.post("").text(&data).compress(Encoding::Gzip));
+
+    assert_eq!(config.headers.get("Content-Encoding"), Some(&"gzip".to_string()));
+    let RequestBody::Binary(compressed) = config.body else {
+        panic!("Expected a compressed binary body, got {:?}", config.body);
+    };
+    let mut decompressed = String::new();
+    GzDecoder::new(compressed.as_slice()).read_to_string(&mut decompressed).unwrap();
+    assert_eq!(decompressed, data);
+}
+
+#[cfg(not(feature = "compression-fallback"))]
+#[test]
+fn test_send_fails_without_an_encoder() {
+    use crate::HttpError;
+
+    // Native builds have no CompressionStream either
+    let data = telemetry();
+    let result = futures::executor::block_on(HttpClient::new().post("").text(&data).compress(Encoding::Gzip).send());
+    assert!(matches!(result, Err(HttpError::Configuration { .. })));
+}
+
+#[test]
+fn test_send_skips_bodies_below_threshold() {
+    let config = sent_config(|client| client.post("").text("short").compress(Encoding::Gzip));
+
+    assert!(!config.headers.contains_key("Content-Encoding"));
+    assert!(matches!(config.body, RequestBody::Text(ref text) if text == "short"));
+}
+
+#[test]
+fn test_send_skips_bodies_with_content_encoding() {
+    let data = telemetry();
+    let config = sent_config(|client| {
+        client
+            .post("")
+            .header("content-encoding", "br")
+            .text(&data)
+            .compress_above(Encoding::Gzip, 0)
+    });
+
+    assert_eq!(config.headers.get("content-encoding"), Some(&"br".to_string()));
+    assert!(!config.headers.contains_key("Content-Encoding"));
+    assert!(matches!(config.body, RequestBody::Text(ref text) if *text == data));
+}
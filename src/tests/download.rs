@@ -0,0 +1,52 @@
+use crate::download::{range_header, split_ranges};
+use crate::{ContentRange, HttpClient};
+
+#[test]
+fn test_range_header() {
+    assert_eq!(range_header(0, Some(99)), "bytes=0-99");
+    assert_eq!(range_header(100, None), "bytes=100-");
+}
+
+#[test]
+fn test_content_range_parse() {
+    assert_eq!(
+        ContentRange::parse("bytes 0-99/1000"),
+        Some(ContentRange { start: 0, end: 99, total: Some(1000) })
+    );
+    assert_eq!(
+        ContentRange::parse("bytes 100-199/*"),
+        Some(ContentRange { start: 100, end: 199, total: None })
+    );
+    assert_eq!(ContentRange::parse("bytes 0-99/1000").unwrap().len(), 100);
+    
+    assert_eq!(ContentRange::parse("bytes */1000"), None);
+    assert_eq!(ContentRange::parse("bytes 99-0/1000"), None);
+    assert_eq!(ContentRange::parse("bytes 0-1000/1000"), None);
+    assert_eq!(ContentRange::parse("items 0-9/10"), None);
+}
+
+#[test]
+fn test_split_ranges() {
+    assert_eq!(split_ranges(100, 350, 100), vec![(100, 199), (200, 299), (300, 349)]);
+    assert_eq!(split_ranges(300, 300, 100), Vec::<(u64, u64)>::new());
+}
+
+#[test]
+fn test_range_request_headers() {
+    let builder = HttpClient::new()
+        .get("/exports/report.csv")
+        .range(1024, None)
+        .if_range("\"v1\"");
+    
+    assert_eq!(builder.config.headers.get("Range"), Some(&"bytes=1024-".to_string()));
+    assert_eq!(builder.config.headers.get("If-Range"), Some(&"\"v1\"".to_string()));
+}
+
+#[test]
+fn test_range_download_resume_state() {
+    let download = HttpClient::new()
+        .download("/exports/report.csv")
+        .resume_from(b"id,name\n".to_vec(), "\"v1\"");
+    
+    assert_eq!(download.partial(), (b"id,name\n".to_vec(), Some("\"v1\"".to_string())));
+}
@@ -0,0 +1,93 @@
+use serde::Deserialize;
+use serde_json::json;
+use crate::graphql::{decode_response, operation_name, query_hash};
+use crate::{GraphQLLocation, GraphQLPathSegment, HttpClient, HttpError};
+
+const QUERY: &str = "query GetUser($id: ID!) { user(id: $id) { name } }";
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct UserData {
+    user: User,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct User {
+    name: String,
+}
+
+#[test]
+fn test_graphql_operation_name() {
+    assert_eq!(operation_name(QUERY), Some("GetUser".to_string()));
+    assert_eq!(operation_name("mutation CreatePost{ createPost { id } }"), Some("CreatePost".to_string()));
+    assert_eq!(operation_name("# comment\nquery { viewer { id } }"), None);
+    assert_eq!(operation_name("{ viewer { id } }"), None);
+    assert_eq!(
+        operation_name("fragment Fields on User { query } query Search { search { ...Fields } }"),
+        Some("Search".to_string())
+    );
+}
+
+#[test]
+fn test_graphql_query_hash() {
+    assert_eq!(
+        query_hash("{ __typename }"),
+        "7f56e67dd21ab3f30d1ff8b7bed08893f0a0db86449836189b361dd1e56ddb4b"
+    );
+}
+
+#[test]
+fn test_graphql_request_body() {
+    let operation = HttpClient::new()
+        .graphql("/graphql", QUERY)
+        .variables(&json!({ "id": "42" }))
+        .unwrap();
+    
+    assert_eq!(operation.body(true), json!({
+        "query": QUERY,
+        "variables": { "id": "42" },
+        "operationName": "GetUser",
+    }));
+    
+    let persisted = operation.persisted_query(true);
+    let body = persisted.body(false);
+    assert!(body.get("query").is_none());
+    assert_eq!(body["extensions"]["persistedQuery"]["sha256Hash"], json!(query_hash(QUERY)));
+    assert_eq!(persisted.body(true)["query"], json!(QUERY));
+}
+
+#[test]
+fn test_graphql_decode_data() {
+    let data: UserData = decode_response(r#"{"data":{"user":{"name":"Ada"}}}"#).unwrap();
+    assert_eq!(data, UserData { user: User { name: "Ada".to_string() } });
+}
+
+#[test]
+fn test_graphql_decode_errors() {
+    let body = r#"{
+        "data": {"user": null},
+        "errors": [{
+            "message": "Not authorized",
+            "locations": [{"line": 1, "column": 30}],
+            "path": ["user", 0, "name"],
+            "extensions": {"code": "FORBIDDEN"}
+        }]
+    }"#;
+    
+    match decode_response::<UserData>(body) {
+        Err(HttpError::GraphQL { errors, data }) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].locations, vec![GraphQLLocation { line: 1, column: 30 }]);
+            assert_eq!(errors[0].path, vec![
+                GraphQLPathSegment::Field("user".to_string()),
+                GraphQLPathSegment::Index(0),
+                GraphQLPathSegment::Field("name".to_string()),
+            ]);
+            assert_eq!(errors[0].code(), Some("FORBIDDEN"));
+            assert_eq!(data, Some(json!({"user": null})));
+            
+            let error = HttpError::GraphQL { errors, data: None };
+            assert_eq!(error.to_string(), "GraphQL error: Not authorized (at user.0.name)");
+        }
+        other => panic!("Expected GraphQL error, got {:?}", other),
+    }
+}
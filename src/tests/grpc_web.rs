@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crate::grpc_web::{decode_unary, encode_frame, grpc_status, parse_trailers};
+use crate::{GrpcFrame, GrpcWebDecoder, GrpcWebMode, HttpClient, HttpError};
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct HelloReply {
+    #[prost(string, tag = "1")]
+    message: String,
+}
+
+fn trailer_frame(trailers: &str) -> Vec<u8> {
+    let mut frame = vec![0x80];
+    frame.extend_from_slice(&(trailers.len() as u32).to_be_bytes());
+    frame.extend_from_slice(trailers.as_bytes());
+    frame
+}
+
+fn reply_body(message: &str, trailers: &str) -> Vec<u8> {
+    let reply = HelloReply { message: message.to_string() };
+    let mut body = encode_frame(&prost::Message::encode_to_vec(&reply));
+    body.extend(trailer_frame(trailers));
+    body
+}
+
+#[test]
+fn test_grpc_web_encode_frame() {
+    assert_eq!(encode_frame(b"abc"), vec![0, 0, 0, 0, 3, b'a', b'b', b'c']);
+    assert_eq!(encode_frame(b""), vec![0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn test_grpc_web_decoder_handles_split_frames() {
+    let body = reply_body("hi", "grpc-status: 0\r\n");
+    let mut decoder = GrpcWebDecoder::new(GrpcWebMode::Binary);
+
+    assert!(decoder.feed(&body[..3]).unwrap().is_empty());
+    let frames = decoder.feed(&body[3..10]).unwrap();
+    assert_eq!(frames.len(), 1);
+    assert!(matches!(frames[0], GrpcFrame::Data(_)));
+
+    let frames = decoder.feed(&body[10..]).unwrap();
+    assert_eq!(frames, vec![GrpcFrame::Trailers(HashMap::from([("grpc-status".to_string(), "0".to_string())]))]);
+    assert!(decoder.finish().is_ok());
+
+    let mut truncated = GrpcWebDecoder::new(GrpcWebMode::Binary);
+    truncated.feed(&body[..body.len() - 1]).unwrap();
+    assert_eq!(truncated.finish(), Err(HttpError::InvalidResponse));
+}
+
+#[test]
+fn test_grpc_web_text_mode_with_padded_chunks() {
+    let reply = HelloReply { message: "text".to_string() };
+    // Proxies may base64 encode each frame separately, padding included
+    let text = format!(
+        "{}{}",
+        STANDARD.encode(encode_frame(&prost::Message::encode_to_vec(&reply))),
+        STANDARD.encode(trailer_frame("grpc-status:0\r\n"))
+    );
+    assert!(text[..text.len() - 4].contains('='));
+
+    let mut decoder = GrpcWebDecoder::new(GrpcWebMode::Text);
+    let mut frames = Vec::new();
+    for chunk in text.as_bytes().chunks(7) {
+        frames.extend(decoder.feed(chunk).unwrap());
+    }
+    assert_eq!(frames.len(), 2);
+    assert!(decoder.finish().is_ok());
+
+    let decoded: HelloReply = decode_unary(GrpcWebMode::Text, &HashMap::new(), text.as_bytes()).unwrap();
+    assert_eq!(decoded, reply);
+}
+
+#[test]
+fn test_grpc_web_parse_trailers() {
+    let trailers = parse_trailers(b"Grpc-Status: 5\r\ngrpc-message: not%20found\r\n");
+    assert_eq!(trailers.get("grpc-status").map(String::as_str), Some("5"));
+    assert_eq!(trailers.get("grpc-message").map(String::as_str), Some("not%20found"));
+}
+
+#[test]
+fn test_grpc_web_status_mapping() {
+    assert_eq!(grpc_status(&HashMap::new()), None);
+    assert_eq!(grpc_status(&parse_trailers(b"grpc-status: 0\r\n")), Some(Ok(())));
+
+    let error = grpc_status(&parse_trailers(b"grpc-status: 5\r\ngrpc-message: user%20not%20found\r\n"));
+    assert_eq!(error, Some(Err(HttpError::Grpc { code: 5, message: "user not found".to_string() })));
+    assert_eq!(error.unwrap().unwrap_err().to_string(), "gRPC error 5: user not found");
+}
+
+#[test]
+fn test_grpc_web_decode_unary() {
+    let body = reply_body("hello", "grpc-status: 0\r\n");
+    let reply: HelloReply = decode_unary(GrpcWebMode::Binary, &HashMap::new(), &body).unwrap();
+    assert_eq!(reply.message, "hello");
+
+    let body = reply_body("ignored", "grpc-status: 7\r\ngrpc-message: denied\r\n");
+    let error = decode_unary::<HelloReply>(GrpcWebMode::Binary, &HashMap::new(), &body);
+    assert_eq!(error, Err(HttpError::Grpc { code: 7, message: "denied".to_string() }));
+
+    // Trailers-only responses carry the status in the headers
+    let headers = HashMap::from([("Grpc-Status".to_string(), "16".to_string())]);
+    let error = decode_unary::<HelloReply>(GrpcWebMode::Binary, &headers, b"");
+    assert!(matches!(error, Err(HttpError::Grpc { code: 16, .. })));
+
+    // A body without any status is malformed
+    let body = encode_frame(b"");
+    let error = decode_unary::<HelloReply>(GrpcWebMode::Binary, &HashMap::new(), &body);
+    assert_eq!(error, Err(HttpError::InvalidResponse));
+}
+
+#[test]
+fn test_grpc_web_request_body() {
+    let request = HelloReply { message: "x".to_string() };
+    let call = HttpClient::new().grpc_web("/helloworld.Greeter/SayHello").message(&request);
+    let binary = call.body();
+    assert_eq!(binary, encode_frame(&prost::Message::encode_to_vec(&request)));
+
+    let call = call.mode(GrpcWebMode::Text);
+    assert_eq!(call.body(), STANDARD.encode(&binary).into_bytes());
+}
+
+#[test]
+fn test_grpc_web_server_streaming_waits_for_scheduler_slot() {
+    use crate::{Priority, RequestScheduler};
+    use super::test::yield_once;
+
+    let scheduler = RequestScheduler::new(1);
+    let client = HttpClient::new().scheduler(scheduler.clone());
+
+    let (result, _) = futures::executor::block_on(async {
+        let permit = scheduler.acquire(Priority::Normal).await;
+        futures::future::join(client.grpc_web("").server_streaming::<HelloReply>(), async {
+            yield_once().await;
+            // The call is queued behind the held slot instead of bypassing it
+            assert_eq!(scheduler.queued(), 1);
+            drop(permit);
+        })
+        .await
+    });
+
+    assert!(matches!(result, Err(HttpError::InvalidUrl { .. })));
+    assert_eq!(scheduler.in_flight(), 0);
+}
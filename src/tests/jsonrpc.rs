@@ -0,0 +1,97 @@
+use futures::executor::block_on;
+use serde_json::json;
+use crate::jsonrpc::{decode_response_object, error_status_body, match_batch_responses, request_object};
+use crate::{HttpClient, HttpError};
+
+#[test]
+fn test_jsonrpc_request_object() {
+    assert_eq!(
+        request_object(Some(7), "subtract", json!([42, 23])),
+        json!({ "jsonrpc": "2.0", "method": "subtract", "params": [42, 23], "id": 7 })
+    );
+
+    // Notifications carry no id, and null params are omitted
+    assert_eq!(
+        request_object(None, "heartbeat", json!(null)),
+        json!({ "jsonrpc": "2.0", "method": "heartbeat" })
+    );
+}
+
+#[test]
+fn test_jsonrpc_decode_response_object() {
+    let result = decode_response_object(&json!({ "jsonrpc": "2.0", "result": 19, "id": 1 }));
+    assert_eq!(result, Ok(json!(19)));
+
+    // A null result is still a result
+    let result = decode_response_object(&json!({ "jsonrpc": "2.0", "result": null, "id": 1 }));
+    assert_eq!(result, Ok(json!(null)));
+
+    let error = decode_response_object(&json!({
+        "jsonrpc": "2.0",
+        "error": { "code": -32601, "message": "Method not found", "data": "subtrac" },
+        "id": 1
+    }));
+    assert_eq!(
+        error,
+        Err(HttpError::JsonRpc { code: -32601, message: "Method not found".to_string(), data: Some(json!("subtrac")) })
+    );
+    assert_eq!(error.unwrap_err().to_string(), "JSON-RPC error -32601: Method not found");
+
+    assert_eq!(decode_response_object(&json!({ "jsonrpc": "2.0", "id": 1 })), Err(HttpError::InvalidResponse));
+}
+
+#[test]
+fn test_jsonrpc_match_batch_responses() {
+    // Responses may arrive in any order
+    let responses = json!([
+        { "jsonrpc": "2.0", "result": "second", "id": 2 },
+        { "jsonrpc": "2.0", "error": { "code": -32602, "message": "Invalid params" }, "id": 3 },
+        { "jsonrpc": "2.0", "result": "first", "id": 1 }
+    ]);
+    let results = match_batch_responses(&[1, 2, 3, 4], &responses);
+
+    assert_eq!(results[&1], Ok(json!("first")));
+    assert_eq!(results[&2], Ok(json!("second")));
+    assert!(matches!(results[&3], Err(HttpError::JsonRpc { code: -32602, .. })));
+    assert_eq!(results[&4], Err(HttpError::InvalidResponse));
+}
+
+#[test]
+fn test_jsonrpc_batch_level_error() {
+    let response = json!({ "jsonrpc": "2.0", "error": { "code": -32700, "message": "Parse error" }, "id": null });
+    let results = match_batch_responses(&[1, 2], &response);
+
+    for id in [1, 2] {
+        assert!(matches!(results[&id], Err(HttpError::JsonRpc { code: -32700, .. })));
+    }
+}
+
+#[test]
+fn test_jsonrpc_dropped_batch_cancels_calls() {
+    let rpc = HttpClient::new().json_rpc("/rpc");
+    let batch = rpc.batch();
+    let call = batch.call::<_, i64>("sum", &[1, 2]).unwrap();
+    batch.notify("log", &json!({ "level": "info" })).unwrap();
+    assert_eq!(batch.len(), 2);
+
+    drop(batch);
+    assert_eq!(block_on(call), Err(HttpError::Cancelled));
+}
+
+#[test]
+fn test_jsonrpc_error_status_body() {
+    let body = r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}"#;
+    let response = error_status_body(body).unwrap();
+    assert!(matches!(
+        decode_response_object(&response),
+        Err(HttpError::JsonRpc { code: -32601, .. })
+    ));
+
+    // Batches keep their response array
+    assert!(error_status_body(r#"[{"jsonrpc":"2.0","result":1,"id":1}]"#).is_some());
+
+    // Plain error pages are not JSON-RPC responses
+    assert!(error_status_body("<html>Bad Gateway</html>").is_none());
+    assert!(error_status_body(r#"{"detail":"Bad Gateway"}"#).is_none());
+    assert!(error_status_body(r#"{"jsonrpc":"2.0","error":null,"id":1}"#).is_none());
+}
@@ -1 +1,23 @@
 pub mod test;
+pub mod scheduler;
+pub mod rate_limit;
+pub mod circuit_breaker;
+pub mod query;
+pub mod path;
+pub mod resolve;
+pub mod multipart;
+pub mod tus;
+pub mod presigned;
+pub mod download;
+pub mod save;
+pub mod sse;
+pub mod ws;
+pub mod ndjson;
+pub mod graphql;
+pub mod jsonrpc;
+#[cfg(feature = "grpc-web")]
+pub mod grpc_web;
+pub mod codec;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+pub mod compression;
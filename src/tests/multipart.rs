@@ -0,0 +1,87 @@
+use crate::{HttpClient, Multipart, Part, RequestBody};
+
+#[test]
+fn test_multipart_serialization() {
+    let multipart = Multipart::with_boundary("XyZ")
+        .text("title", "Holiday")
+        .file("photos", b"PNG1".to_vec(), "a.png", "image/png")
+        .file("photos", b"PNG2".to_vec(), "b \"2\".png", "image/png")
+        .part(Part::bytes("meta", b"{}".to_vec()).content_type("application/json").header("X-Trace", "1"));
+    
+    let expected = concat!(
+        "--XyZ\r\n",
+        "Content-Disposition: form-data; name=\"title\"\r\n",
+        "\r\n",
+        "Holiday\r\n",
+        "--XyZ\r\n",
+        "Content-Disposition: form-data; name=\"photos\"; filename=\"a.png\"\r\n",
+        "Content-Type: image/png\r\n",
+        "\r\n",
+        "PNG1\r\n",
+        "--XyZ\r\n",
+        "Content-Disposition: form-data; name=\"photos\"; filename=\"b %222%22.png\"\r\n",
+        "Content-Type: image/png\r\n",
+        "\r\n",
+        "PNG2\r\n",
+        "--XyZ\r\n",
+        "Content-Disposition: form-data; name=\"meta\"\r\n",
+        "Content-Type: application/json\r\n",
+        "X-Trace: 1\r\n",
+        "\r\n",
+        "{}\r\n",
+        "--XyZ--\r\n",
+    );
+    
+    assert_eq!(String::from_utf8(multipart.to_bytes()).unwrap(), expected);
+    assert_eq!(multipart.parts().len(), 4);
+    assert_eq!(multipart.parts()[2].filename(), Some("b \"2\".png"));
+}
+
+#[test]
+fn test_multipart_headers_cannot_inject_lines() {
+    let multipart = Multipart::with_boundary("XyZ").part(
+        Part::bytes("doc", b"%PDF".to_vec())
+            .file_name("evil.pdf\r\nX-Injected: 1")
+            .content_type("application/pdf\r\nX-Injected: 2")
+            .header("X-Note\r\nX-Injected", "a\r\n\r\nbody")
+            .header("\r\n", "dropped"),
+    );
+    
+    let expected = concat!(
+        "--XyZ\r\n",
+        "Content-Disposition: form-data; name=\"doc\"; filename=\"evil.pdf%0D%0AX-Injected: 1\"\r\n",
+        "Content-Type: application/pdf  X-Injected: 2\r\n",
+        "X-NoteX-Injected: a    body\r\n",
+        "\r\n",
+        "%PDF\r\n",
+        "--XyZ--\r\n",
+    );
+    assert_eq!(String::from_utf8(multipart.to_bytes()).unwrap(), expected);
+}
+
+#[test]
+fn test_multipart_generates_unique_boundaries() {
+    let first = Multipart::new();
+    let second = Multipart::new();
+    
+    assert!(first.boundary().starts_with("----httpcalls"));
+    assert_ne!(first.boundary(), second.boundary());
+}
+
+#[test]
+fn test_request_builder_multipart() {
+    let multipart = Multipart::with_boundary("b0undary")
+        .file("document", b"%PDF".to_vec(), "report.pdf", "application/pdf");
+    let expected = multipart.to_bytes();
+    
+    let builder = HttpClient::new().post("/api/upload").multipart(multipart);
+    
+    assert_eq!(
+        builder.config.headers.get("Content-Type"),
+        Some(&"multipart/form-data; boundary=b0undary".to_string())
+    );
+    match &builder.config.body {
+        RequestBody::Binary(data) => assert_eq!(data, &expected),
+        _ => panic!("Expected binary body"),
+    }
+}
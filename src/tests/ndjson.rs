@@ -0,0 +1,67 @@
+use serde::Deserialize;
+use crate::{HttpClient, HttpError, NdjsonDecoder};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Row {
+    id: u32,
+}
+
+#[test]
+fn test_ndjson_decodes_lines_split_across_chunks() {
+    let mut decoder = NdjsonDecoder::<Row>::new();
+    
+    assert!(decoder.feed(b"{\"id\":").is_empty());
+    let rows: Vec<_> = decoder.feed(b"1}\r\n{\"id\":2}\n{\"i").into_iter().map(Result::unwrap).collect();
+    assert_eq!(rows, vec![Row { id: 1 }, Row { id: 2 }]);
+    
+    assert!(decoder.feed(b"d\":3}").is_empty());
+    assert_eq!(decoder.finish().unwrap().unwrap(), Row { id: 3 });
+    assert!(decoder.finish().is_none());
+}
+
+#[test]
+fn test_ndjson_reports_line_numbers_and_skips_blank_lines() {
+    let mut decoder = NdjsonDecoder::<Row>::new();
+    let items = decoder.feed(b"{\"id\":1}\n\n  \n{\"id\":oops}\n{\"id\":5}\n");
+    
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[0], Ok(Row { id: 1 }));
+    match &items[1] {
+        Err(HttpError::InvalidLine { line, .. }) => assert_eq!(*line, 4),
+        other => panic!("Expected invalid line error, got {:?}", other),
+    }
+    assert_eq!(items[2], Ok(Row { id: 5 }));
+}
+
+#[test]
+fn test_ndjson_byte_by_byte_feed_and_empty_finish() {
+    let mut decoder = NdjsonDecoder::<Row>::new();
+    let mut items = Vec::new();
+    for byte in b"{\"id\":1}\n{\"id\":2}\n" {
+        items.extend(decoder.feed(std::slice::from_ref(byte)));
+    }
+    assert_eq!(items, vec![Ok(Row { id: 1 }), Ok(Row { id: 2 })]);
+    
+    // Finishing with nothing buffered does not count a line
+    assert!(decoder.finish().is_none());
+    match decoder.feed(b"oops\n").as_slice() {
+        [Err(HttpError::InvalidLine { line, .. })] => assert_eq!(*line, 3),
+        other => panic!("Expected invalid line error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_ndjson_open_failure_reported_once() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    
+    let calls = Rc::new(Cell::new(0));
+    let client = {
+        let calls = calls.clone();
+        HttpClient::new().on_error(move |_, _| calls.set(calls.get() + 1))
+    };
+    
+    let result = futures::executor::block_on(client.get("").send_stream::<Row>());
+    assert!(matches!(result, Err(HttpError::InvalidUrl { .. })));
+    assert_eq!(calls.get(), 1);
+}
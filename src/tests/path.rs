@@ -0,0 +1,61 @@
+use crate::path::{expand_template, is_template};
+use crate::{HttpClient, HttpError};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[test]
+fn test_path_params_are_substituted_and_encoded() {
+    let builder = HttpClient::new()
+        .base_url("https://api.example.com")
+        .get("/users/{id}/posts/{post_id}")
+        .path_param("id", 5)
+        .path_param("post_id", "hello world/2");
+    
+    assert_eq!(
+        builder.config.request_url().unwrap(),
+        "https://api.example.com/users/5/posts/hello%20world%2F2"
+    );
+    assert_eq!(
+        builder.config.path_template(),
+        Some("https://api.example.com/users/{id}/posts/{post_id}")
+    );
+}
+
+#[test]
+fn test_unfilled_placeholder_is_invalid_url() {
+    let builder = HttpClient::new()
+        .get("/users/{id}/posts/{post_id}")
+        .path_param("id", 5);
+    
+    assert!(matches!(builder.config.request_url(), Err(HttpError::InvalidUrl { .. })));
+    
+    let result = futures::executor::block_on(builder.send());
+    assert!(matches!(result, Err(HttpError::InvalidUrl { .. })));
+}
+
+#[test]
+fn test_path_params_from_struct() {
+    #[derive(Serialize)]
+    struct PostPath {
+        id: u64,
+        post_id: String,
+    }
+    
+    let builder = HttpClient::new()
+        .get("/users/{id}/posts/{post_id}")
+        .path_params(&PostPath { id: 7, post_id: "a/b".to_string() })
+        .unwrap()
+        .query("draft", true);
+    
+    assert_eq!(builder.config.request_url().unwrap(), "/users/7/posts/a%2Fb?draft=true");
+}
+
+#[test]
+fn test_plain_urls_are_not_templates() {
+    assert!(!is_template("/users/5"));
+    assert!(!is_template("/search?q={}"));
+    assert!(is_template("/users/{user_id}"));
+    
+    assert_eq!(HttpClient::new().get("/users/5").config.path_template(), None);
+    assert_eq!(expand_template("/users/5", &HashMap::new()).unwrap(), "/users/5");
+}
@@ -0,0 +1,123 @@
+use futures::executor::block_on;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use crate::presigned::{part_ranges, CompletedPart, PartRange, MAX_PARTS};
+use crate::{HttpClient, HttpError};
+use super::test::yield_once;
+
+#[test]
+fn test_part_ranges() {
+    let ranges = part_ranges(10, 4);
+    
+    assert_eq!(ranges, vec![
+        PartRange { part_number: 1, start: 0, end: 4 },
+        PartRange { part_number: 2, start: 4, end: 8 },
+        PartRange { part_number: 3, start: 8, end: 10 },
+    ]);
+    assert_eq!(ranges[2].len(), 2);
+    assert_eq!(part_ranges(8, 4).len(), 2);
+}
+
+#[test]
+fn test_part_ranges_empty_data_has_one_part() {
+    assert_eq!(part_ranges(0, 4), vec![PartRange { part_number: 1, start: 0, end: 0 }]);
+}
+
+#[test]
+fn test_presigned_upload_requires_part_url() {
+    let result = block_on(HttpClient::new().presigned_upload(vec![0; 16]).start());
+    
+    assert!(matches!(result, Err(HttpError::Configuration { .. })));
+}
+
+#[test]
+fn test_presigned_upload_rejects_too_many_parts() {
+    let upload = HttpClient::new()
+        .presigned_upload(vec![0; MAX_PARTS + 1])
+        .part_size(1)
+        .part_url(|part_number| async move { Ok(format!("https://bucket.s3.amazonaws.com/key?partNumber={}", part_number)) });
+    
+    assert_eq!(upload.parts().len(), MAX_PARTS + 1);
+    assert!(matches!(block_on(upload.start()), Err(HttpError::Configuration { .. })));
+}
+
+#[test]
+fn test_presigned_parts_upload_in_parallel_and_in_order() {
+    let upload = HttpClient::new().presigned_upload(vec![0; 10]).part_size(4).concurrency(2);
+    let in_flight = Cell::new(0);
+    let max_in_flight = Cell::new(0);
+    
+    let parts = block_on(upload.upload_parts(upload.parts(), |range| {
+        let (in_flight, max_in_flight) = (&in_flight, &max_in_flight);
+        async move {
+            in_flight.set(in_flight.get() + 1);
+            max_in_flight.set(max_in_flight.get().max(in_flight.get()));
+            // Part 1 finishes last
+            for _ in 0..(4 - range.part_number) {
+                yield_once().await;
+            }
+            in_flight.set(in_flight.get() - 1);
+            Ok(CompletedPart { part_number: range.part_number, etag: format!("\"etag-{}\"", range.part_number) })
+        }
+    }))
+    .unwrap();
+    
+    assert_eq!(max_in_flight.get(), 2);
+    assert_eq!(parts, vec![
+        CompletedPart { part_number: 1, etag: "\"etag-1\"".to_string() },
+        CompletedPart { part_number: 2, etag: "\"etag-2\"".to_string() },
+        CompletedPart { part_number: 3, etag: "\"etag-3\"".to_string() },
+    ]);
+}
+
+#[test]
+fn test_presigned_part_retried_on_its_own() {
+    let upload = HttpClient::new().presigned_upload(vec![0; 8]).part_size(4).part_retry(2, 0);
+    let attempts = RefCell::new(vec![0, 0]);
+    
+    let parts = block_on(upload.upload_parts(upload.parts(), |range| {
+        let attempts = &attempts;
+        async move {
+            let attempt = {
+                let mut attempts = attempts.borrow_mut();
+                attempts[range.part_number as usize - 1] += 1;
+                attempts[range.part_number as usize - 1]
+            };
+            if range.part_number == 2 && attempt == 1 {
+                return Err(HttpError::Network { message: "connection reset".to_string() });
+            }
+            Ok(CompletedPart { part_number: range.part_number, etag: format!("etag-{}", range.part_number) })
+        }
+    }))
+    .unwrap();
+    
+    assert_eq!(*attempts.borrow(), vec![1, 2]);
+    assert_eq!(parts.len(), 2);
+    
+    // Errors a retry cannot fix fail the part at once
+    let attempts = Cell::new(0);
+    let result = block_on(upload.upload_parts(vec![PartRange { part_number: 1, start: 0, end: 4 }], |_| {
+        attempts.set(attempts.get() + 1);
+        async { Err(HttpError::Http { status: 404, message: "Not Found".to_string(), body: None }) }
+    }));
+    assert!(matches!(result, Err(HttpError::Http { status: 404, .. })));
+    assert_eq!(attempts.get(), 1);
+}
+
+#[test]
+fn test_presigned_upload_reports_final_error_once() {
+    let reported = Rc::new(RefCell::new(Vec::new()));
+    let client = {
+        let reported = reported.clone();
+        HttpClient::new().on_error(move |_, config| reported.borrow_mut().push(config.call_name.clone()))
+    };
+    
+    let upload = client
+        .presigned_upload(vec![0; 8])
+        .part_size(4)
+        .part_retry(1, 0)
+        .part_url(|_| async { Err(HttpError::Network { message: "presign failed".to_string() }) });
+    
+    assert!(matches!(block_on(upload.start()), Err(HttpError::Network { .. })));
+    assert_eq!(*reported.borrow(), vec![Some("presigned_upload".to_string())]);
+}
@@ -0,0 +1,37 @@
+use crate::protobuf::PROTOBUF_CONTENT_TYPE;
+use crate::{HttpError, HttpMethod, HttpResponse, RequestBody, RequestBuilder};
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Reading {
+    #[prost(string, tag = "1")]
+    sensor: String,
+    #[prost(double, tag = "2")]
+    value: f64,
+}
+
+fn response(bytes: Vec<u8>) -> HttpResponse {
+    HttpResponse::new(200, "https://api.example.com/readings/1", bytes).with_header("content-type", PROTOBUF_CONTENT_TYPE)
+}
+
+#[test]
+fn test_protobuf_request_body() {
+    let reading = Reading { sensor: "t1".to_string(), value: 21.5 };
+    let builder = RequestBuilder::new(HttpMethod::Post, "https://api.example.com/readings").protobuf(&reading);
+
+    assert_eq!(builder.config.headers.get("Content-Type").map(String::as_str), Some("application/x-protobuf"));
+    match builder.config.body {
+        RequestBody::Binary(ref bytes) => assert_eq!(*bytes, prost::Message::encode_to_vec(&reading)),
+        _ => panic!("Expected binary body"),
+    }
+}
+
+#[test]
+fn test_protobuf_response_decoding() {
+    let reading = Reading { sensor: "t1".to_string(), value: 21.5 };
+    let decoded: Reading = response(prost::Message::encode_to_vec(&reading)).protobuf().unwrap();
+    assert_eq!(decoded, reading);
+
+    // Truncated length-delimited field
+    let error = response(vec![0x0a, 0x05, b'a']).protobuf::<Reading>();
+    assert!(matches!(error, Err(HttpError::Serialization { .. })));
+}
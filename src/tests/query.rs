@@ -0,0 +1,141 @@
+use crate::query::{append_query, encode_component, encode_query, to_query_params};
+use crate::{ArrayFormat, HttpClient, HttpError, QueryValue};
+use serde::Serialize;
+
+#[test]
+fn test_encode_component() {
+    assert_eq!(encode_component("hello world"), "hello%20world");
+    assert_eq!(encode_component("a&b=c"), "a%26b%3Dc");
+    assert_eq!(encode_component("safe-._~"), "safe-._~");
+    assert_eq!(encode_component("ünïcödé"), "%C3%BCn%C3%AFc%C3%B6d%C3%A9");
+}
+
+#[test]
+fn test_query_parameters_are_encoded() {
+    let builder = HttpClient::new()
+        .get("/api/search")
+        .query("q", "rust & wasm")
+        .query("page", 2);
+    
+    assert_eq!(builder.config.url, "/api/search");
+    assert_eq!(builder.config.request_url().unwrap(), "/api/search?q=rust%20%26%20wasm&page=2");
+}
+
+#[test]
+fn test_query_merges_with_existing_query() {
+    let builder = HttpClient::new()
+        .get("/api/search?sort=desc#results")
+        .query_pairs([("q", "shoes"), ("size", "42")]);
+    
+    assert_eq!(builder.config.request_url().unwrap(), "/api/search?sort=desc&q=shoes&size=42#results");
+    assert_eq!(append_query("/api?", "a=1"), "/api?a=1");
+    assert_eq!(append_query("/api", ""), "/api");
+}
+
+#[test]
+fn test_array_formats() {
+    let params = vec![("id".to_string(), QueryValue::List(vec!["1".to_string(), "2".to_string()]))];
+    
+    assert_eq!(encode_query(&params, ArrayFormat::Repeat), "id=1&id=2");
+    assert_eq!(encode_query(&params, ArrayFormat::Brackets), "id%5B%5D=1&id%5B%5D=2");
+    assert_eq!(encode_query(&params, ArrayFormat::Indices), "id%5B0%5D=1&id%5B1%5D=2");
+    assert_eq!(encode_query(&params, ArrayFormat::Comma), "id=1,2");
+    
+    let builder = HttpClient::new()
+        .get("/api/items")
+        .query_array("tag", ["a b", "c"])
+        .array_format(ArrayFormat::Comma);
+    assert_eq!(builder.config.request_url().unwrap(), "/api/items?tag=a%20b,c");
+}
+
+#[test]
+fn test_query_struct() {
+    #[derive(Serialize)]
+    struct Filters {
+        search: String,
+        page: u32,
+        active: bool,
+        tags: Vec<&'static str>,
+        cursor: Option<String>,
+    }
+    
+    let filters = Filters {
+        search: "red shoes".to_string(),
+        page: 3,
+        active: true,
+        tags: vec!["sale", "new"],
+        cursor: None,
+    };
+    
+    let builder = HttpClient::new()
+        .get("/api/products")
+        .query_struct(&filters)
+        .unwrap()
+        .array_format(ArrayFormat::Brackets);
+    
+    assert_eq!(
+        builder.config.request_url().unwrap(),
+        "/api/products?search=red%20shoes&page=3&active=true&tags%5B%5D=sale&tags%5B%5D=new"
+    );
+}
+
+#[test]
+fn test_query_struct_nested_and_invalid() {
+    #[derive(Serialize)]
+    struct Range {
+        min: u32,
+        max: u32,
+    }
+    
+    #[derive(Serialize)]
+    struct Filters {
+        price: Range,
+    }
+    
+    let params = to_query_params(&Filters { price: Range { min: 1, max: 9 } }).unwrap();
+    assert_eq!(
+        params,
+        vec![
+            ("price[min]".to_string(), QueryValue::Single("1".to_string())),
+            ("price[max]".to_string(), QueryValue::Single("9".to_string())),
+        ]
+    );
+    
+    assert!(matches!(to_query_params(&vec![1, 2]), Err(HttpError::Serialization { .. })));
+}
+
+#[test]
+fn test_form_body_is_urlencoded() {
+    use crate::RequestBody;
+    
+    #[derive(Serialize)]
+    struct TokenRequest {
+        grant_type: &'static str,
+        username: &'static str,
+        password: &'static str,
+        scope: Vec<&'static str>,
+        client_secret: Option<&'static str>,
+    }
+    
+    let request = TokenRequest {
+        grant_type: "password",
+        username: "jane doe",
+        password: "p@ss&word=1",
+        scope: vec!["read", "write"],
+        client_secret: None,
+    };
+    
+    let builder = HttpClient::new().post("/oauth/token").form(&request).unwrap();
+    
+    assert_eq!(
+        builder.config.headers.get("Content-Type"),
+        Some(&"application/x-www-form-urlencoded".to_string())
+    );
+    match &builder.config.body {
+        RequestBody::UrlEncoded(body) => assert_eq!(
+            body,
+            "grant_type=password&username=jane+doe&password=p%40ss%26word%3D1&scope=read&scope=write"
+        ),
+        _ => panic!("Expected urlencoded body"),
+    }
+}
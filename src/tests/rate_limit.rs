@@ -0,0 +1,80 @@
+use crate::{HttpClient, RateLimit, RateLimiter};
+
+#[test]
+fn test_rate_limit_allows_burst_then_delays() {
+    let limiter = RateLimiter::new().limit(RateLimit::new(2, 1000));
+    
+    assert_eq!(limiter.reserve("https://api.example.com/a", 0.0), 0.0);
+    assert_eq!(limiter.reserve("https://api.example.com/b", 0.0), 0.0);
+    
+    // Bucket is empty: next token arrives after half the interval
+    assert_eq!(limiter.reserve("https://api.example.com/c", 0.0), 500.0);
+    // Requests queue up behind each other
+    assert_eq!(limiter.reserve("https://api.example.com/d", 0.0), 1000.0);
+}
+
+#[test]
+fn test_rate_limit_refills_over_time() {
+    let limiter = RateLimiter::new().limit(RateLimit::new(1, 100).burst(1));
+    
+    assert_eq!(limiter.reserve("/api/a", 0.0), 0.0);
+    assert_eq!(limiter.reserve("/api/b", 50.0), 50.0);
+    assert_eq!(limiter.reserve("/api/c", 1000.0), 0.0);
+}
+
+#[test]
+fn test_rate_limit_per_host() {
+    let limiter = RateLimiter::new()
+        .host_limit("third-party.example.com", RateLimit::new(1, 1000));
+    
+    assert_eq!(limiter.reserve("https://third-party.example.com/v1/a", 0.0), 0.0);
+    assert_eq!(limiter.reserve("https://Third-Party.example.com/v1/b", 0.0), 1000.0);
+    
+    // Other hosts are not limited
+    assert_eq!(limiter.reserve("https://api.example.com/a", 0.0), 0.0);
+    assert_eq!(limiter.reserve("https://api.example.com/b", 0.0), 0.0);
+}
+
+#[test]
+fn test_client_rate_limit_configuration() {
+    let client = HttpClient::new()
+        .rate_limit(RateLimit::new(10, 1000))
+        .rate_limit_host("api.example.com", RateLimit::new(1, 1000));
+    
+    let builder = client.get("https://api.example.com/a");
+    let limiter = builder.rate_limiter.expect("rate limiter applied to request");
+    
+    assert_eq!(limiter.reserve("https://api.example.com/a", 0.0), 0.0);
+    assert_eq!(limiter.reserve("https://api.example.com/b", 0.0), 1000.0);
+    assert_eq!(limiter.reserve("/local", 0.0), 0.0);
+}
+
+#[test]
+fn test_rate_limit_host_ignores_port_and_userinfo() {
+    let limiter = RateLimiter::new().host_limit("api.example.com", RateLimit::new(1, 1000));
+    
+    assert_eq!(limiter.reserve("https://api.example.com:8443/a", 0.0), 0.0);
+    assert_eq!(limiter.reserve("https://user@API.example.com/b", 0.0), 1000.0);
+}
+
+#[test]
+fn test_rate_limit_changes_do_not_affect_clones() {
+    let base = HttpClient::new().rate_limit(RateLimit::new(1, 1000));
+    let strict = base.clone().rate_limit_host("api.example.com", RateLimit::new(1, 5000));
+    
+    // The base client keeps its own limits and buckets
+    let limiter = base.get("/a").rate_limiter.expect("rate limiter applied to request");
+    assert_eq!(limiter.reserve("https://api.example.com/a", 0.0), 0.0);
+    assert_eq!(limiter.reserve("https://api.example.com/b", 0.0), 1000.0);
+    
+    let limiter = strict.get("/a").rate_limiter.expect("rate limiter applied to request");
+    assert_eq!(limiter.reserve("https://api.example.com/a", 0.0), 0.0);
+    assert_eq!(limiter.reserve("https://api.example.com/b", 0.0), 5000.0);
+    
+    // An explicitly shared limiter is still shared
+    let shared = RateLimiter::new().limit(RateLimit::new(1, 1000));
+    let client = HttpClient::new().rate_limiter(shared.clone());
+    assert_eq!(shared.reserve("/a", 0.0), 0.0);
+    let limiter = client.get("/a").rate_limiter.expect("rate limiter applied to request");
+    assert_eq!(limiter.reserve("/b", 0.0), 1000.0);
+}
@@ -0,0 +1,109 @@
+use crate::resolve::{join_url, validate_url};
+use crate::{HttpClient, HttpError};
+
+#[test]
+fn test_join_keeps_base_path() {
+    assert_eq!(join_url("https://api.example.com/v1", "/users").unwrap(), "https://api.example.com/v1/users");
+    assert_eq!(join_url("https://api.example.com/v1/", "users/1").unwrap(), "https://api.example.com/v1/users/1");
+    assert_eq!(join_url("https://api.example.com", "").unwrap(), "https://api.example.com/");
+}
+
+#[test]
+fn test_join_resolves_dot_segments() {
+    assert_eq!(join_url("https://api.example.com/v1/", "../v2/users").unwrap(), "https://api.example.com/v2/users");
+    assert_eq!(join_url("https://api.example.com/v1", "./users/./1").unwrap(), "https://api.example.com/v1/users/1");
+}
+
+#[test]
+fn test_join_absolute_and_protocol_relative() {
+    assert_eq!(join_url("https://api.example.com", "http://other.example.com/x").unwrap(), "http://other.example.com/x");
+    assert_eq!(join_url("https://api.example.com", "ws://live.example.com/feed").unwrap(), "ws://live.example.com/feed");
+    assert_eq!(join_url("https://api.example.com/v1", "//cdn.example.com/img.png").unwrap(), "https://cdn.example.com/img.png");
+}
+
+#[test]
+fn test_join_merges_base_query() {
+    assert_eq!(
+        join_url("https://api.example.com/v1?key=abc", "/users?page=2").unwrap(),
+        "https://api.example.com/v1/users?key=abc&page=2"
+    );
+    assert_eq!(
+        join_url("https://api.example.com/v1?key=abc", "users").unwrap(),
+        "https://api.example.com/v1/users?key=abc"
+    );
+}
+
+#[test]
+fn test_join_preserves_path_templates() {
+    let client = HttpClient::new().base_url("https://api.example.com/v1");
+    let builder = client.get("/users/{id}").path_param("id", 9);
+    
+    assert_eq!(builder.config.url, "https://api.example.com/v1/users/{id}");
+    assert_eq!(builder.config.request_url().unwrap(), "https://api.example.com/v1/users/9");
+}
+
+#[test]
+fn test_validate_url_reports_reason() {
+    match validate_url("", &[]) {
+        Err(HttpError::InvalidUrl { reason, .. }) => assert_eq!(reason, "URL is empty"),
+        other => panic!("Expected invalid URL, got {:?}", other),
+    }
+    
+    match validate_url("ws://live.example.com", &[]) {
+        Err(HttpError::InvalidUrl { reason, .. }) => assert!(reason.contains("unsupported scheme `ws`")),
+        other => panic!("Expected invalid URL, got {:?}", other),
+    }
+    
+    assert!(matches!(validate_url("https://exa mple.com", &[]), Err(HttpError::InvalidUrl { .. })));
+    assert!(validate_url("/api/users", &[]).is_ok());
+    assert!(validate_url("https://api.example.com/users", &[]).is_ok());
+}
+
+#[test]
+fn test_allowed_origins() {
+    let client = HttpClient::new().allowed_origins(["https://API.example.com/", "https://cdn.example.com:443"]);
+    
+    let allowed = client.get("https://api.example.com/users");
+    assert_eq!(allowed.config.allowed_origins, vec!["https://api.example.com", "https://cdn.example.com"]);
+    assert!(validate_url("https://api.example.com/users", &allowed.config.allowed_origins).is_ok());
+    assert!(validate_url("/relative", &allowed.config.allowed_origins).is_ok());
+    
+    let result = futures::executor::block_on(client.get("https://evil.example.com/steal").send());
+    match result {
+        Err(HttpError::InvalidUrl { reason, .. }) => assert!(reason.contains("not allowed")),
+        other => panic!("Expected disallowed origin, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_join_restores_only_template_placeholders() {
+    assert_eq!(
+        join_url("https://api.example.com", "/files/{id}?filter=%7Bx%7D").unwrap(),
+        "https://api.example.com/files/{id}?filter=%7Bx%7D"
+    );
+    assert_eq!(
+        join_url("https://api.example.com", "/a%7Bb%7D/{id}").unwrap(),
+        "https://api.example.com/a%7Bb%7D/{id}"
+    );
+}
+
+#[test]
+fn test_relative_base_url_is_concatenated() {
+    let client = HttpClient::new().base_url("/api");
+    assert_eq!(client.get("/users").config.url, "/api/users");
+}
+
+#[test]
+fn test_allowed_origins_reject_protocol_relative_urls() {
+    let allowed = vec!["https://api.example.com".to_string()];
+
+    for url in ["//evil.example.com/x", "/\\evil.example.com/x", "\\\\evil.example.com/x", "/\t/evil.example.com/x"] {
+        match validate_url(url, &allowed) {
+            Err(HttpError::InvalidUrl { reason, .. }) => assert!(reason.contains("protocol-relative")),
+            other => panic!("Expected protocol-relative URL to be rejected for {:?}, got {:?}", url, other),
+        }
+    }
+
+    assert!(validate_url("//evil.example.com/x", &[]).is_ok());
+    assert!(validate_url("/api/users", &allowed).is_ok());
+}
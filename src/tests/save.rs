@@ -0,0 +1,62 @@
+use crate::save::content_disposition_filename;
+use crate::HttpResponse;
+
+fn response(url: &str, disposition: Option<&str>) -> HttpResponse {
+    let response = HttpResponse::new(200, url, Vec::new());
+    match disposition {
+        Some(disposition) => response.with_header("content-disposition", disposition),
+        None => response,
+    }
+}
+
+#[test]
+fn test_content_disposition_plain_filename() {
+    assert_eq!(content_disposition_filename("attachment; filename=\"report.pdf\""), Some("report.pdf".to_string()));
+    assert_eq!(content_disposition_filename("attachment; filename=report.pdf"), Some("report.pdf".to_string()));
+    assert_eq!(
+        content_disposition_filename("attachment; filename=\"quarterly \\\"final\\\"; v2.pdf\"; size=10"),
+        Some("quarterly \"final\"; v2.pdf".to_string())
+    );
+    assert_eq!(content_disposition_filename("inline"), None);
+}
+
+#[test]
+fn test_content_disposition_rfc5987_filename() {
+    assert_eq!(
+        content_disposition_filename("attachment; filename=\"fallback.txt\"; filename*=UTF-8''na%C3%AFve%20file.txt"),
+        Some("naïve file.txt".to_string())
+    );
+    assert_eq!(
+        content_disposition_filename("attachment; FILENAME*=iso-8859-1'en'%A3%20rates.csv"),
+        Some("£ rates.csv".to_string())
+    );
+    // Undecodable extended values fall back to the plain filename
+    assert_eq!(
+        content_disposition_filename("attachment; filename*=UTF-8''%FF.txt; filename=\"plain.txt\""),
+        Some("plain.txt".to_string())
+    );
+}
+
+#[test]
+fn test_content_disposition_strips_directories() {
+    assert_eq!(
+        content_disposition_filename("attachment; filename=\"../../etc/passwd\""),
+        Some("passwd".to_string())
+    );
+    assert_eq!(
+        content_disposition_filename("attachment; filename=\"C:\\\\Users\\\\report.pdf\""),
+        Some("report.pdf".to_string())
+    );
+}
+
+#[test]
+fn test_save_filename_precedence() {
+    let with_header = response("https://api.example.com/files/42", Some("attachment; filename=\"invoice.pdf\""));
+    assert_eq!(with_header.save_filename(Some("custom.pdf")), "custom.pdf");
+    assert_eq!(with_header.save_filename(None), "invoice.pdf");
+    
+    let without_header = response("https://api.example.com/files/annual%20report.pdf?token=abc", None);
+    assert_eq!(without_header.save_filename(None), "annual report.pdf");
+    
+    assert_eq!(response("https://api.example.com/", None).save_filename(None), "download");
+}
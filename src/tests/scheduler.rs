@@ -0,0 +1,102 @@
+use crate::{HttpClient, Priority, RequestScheduler};
+use futures::executor::block_on;
+use futures::poll;
+use std::task::Poll;
+
+#[test]
+fn test_scheduler_limits_in_flight() {
+    let scheduler = RequestScheduler::new(2);
+    
+    block_on(async {
+        let first = scheduler.acquire(Priority::Normal).await;
+        let _second = scheduler.acquire(Priority::Normal).await;
+        assert_eq!(scheduler.in_flight(), 2);
+        
+        let mut third = Box::pin(scheduler.acquire(Priority::Normal));
+        assert!(poll!(&mut third).is_pending());
+        assert_eq!(scheduler.queued(), 1);
+        
+        drop(first);
+        let _third_permit = match poll!(&mut third) {
+            Poll::Ready(permit) => permit,
+            Poll::Pending => panic!("Expected queued request to get the released slot"),
+        };
+        assert_eq!(scheduler.in_flight(), 2);
+        assert_eq!(scheduler.queued(), 0);
+    });
+}
+
+#[test]
+fn test_scheduler_serves_higher_priority_first() {
+    let scheduler = RequestScheduler::new(1);
+    
+    block_on(async {
+        let running = scheduler.acquire(Priority::Normal).await;
+        
+        let mut low = Box::pin(scheduler.acquire(Priority::Low));
+        let mut high = Box::pin(scheduler.acquire(Priority::High));
+        assert!(poll!(&mut low).is_pending());
+        assert!(poll!(&mut high).is_pending());
+        
+        drop(running);
+        assert!(poll!(&mut low).is_pending());
+        let high_permit = match poll!(&mut high) {
+            Poll::Ready(permit) => permit,
+            Poll::Pending => panic!("Expected high priority request to run first"),
+        };
+        
+        drop(high_permit);
+        assert!(matches!(poll!(&mut low), Poll::Ready(_)));
+    });
+}
+
+#[test]
+fn test_scheduler_skips_abandoned_waiters() {
+    let scheduler = RequestScheduler::new(1);
+    
+    block_on(async {
+        let running = scheduler.acquire(Priority::Normal).await;
+        
+        let mut abandoned = Box::pin(scheduler.acquire(Priority::High));
+        let mut waiting = Box::pin(scheduler.acquire(Priority::Low));
+        assert!(poll!(&mut abandoned).is_pending());
+        assert!(poll!(&mut waiting).is_pending());
+        
+        drop(abandoned);
+        assert_eq!(scheduler.queued(), 1);
+        drop(running);
+        assert!(matches!(poll!(&mut waiting), Poll::Ready(_)));
+    });
+    
+    assert_eq!(scheduler.in_flight(), 0);
+}
+
+#[test]
+fn test_scheduler_forgets_abandoned_waiters() {
+    let scheduler = RequestScheduler::new(1);
+    
+    block_on(async {
+        let running = scheduler.acquire(Priority::Normal).await;
+        
+        let mut abandoned = Box::pin(scheduler.acquire(Priority::Normal));
+        assert!(poll!(&mut abandoned).is_pending());
+        drop(abandoned);
+        assert_eq!(scheduler.queued(), 0);
+        
+        // With nobody left in the queue, the next request takes the free slot directly
+        drop(running);
+        let mut next = Box::pin(scheduler.acquire(Priority::Low));
+        assert!(matches!(poll!(&mut next), Poll::Ready(_)));
+    });
+}
+
+#[test]
+fn test_request_priority_configuration() {
+    let client = HttpClient::new().max_in_flight(4);
+    
+    let builder = client.get("/api/prefetch").priority(Priority::Low);
+    assert_eq!(builder.config.priority, Priority::Low);
+    assert_eq!(builder.scheduler.as_ref().map(|s| s.max_in_flight()), Some(4));
+    
+    assert_eq!(client.get("/api/data").config.priority, Priority::Normal);
+}
@@ -0,0 +1,84 @@
+use serde::Deserialize;
+use crate::{HttpClient, SseEvent, SseParser, SseTransport};
+
+#[test]
+fn test_sse_parser_fields() {
+    let mut parser = SseParser::new();
+    let events = parser.feed(b": keep-alive\nretry: 5000\nevent: update\nid: 7\ndata: first\ndata:second\n\ndata: plain\n\n");
+    
+    assert_eq!(events, vec![
+        SseEvent { event: "update".to_string(), data: "first\nsecond".to_string(), id: Some("7".to_string()) },
+        SseEvent { event: "message".to_string(), data: "plain".to_string(), id: Some("7".to_string()) },
+    ]);
+    assert_eq!(parser.last_event_id(), Some("7"));
+    assert_eq!(parser.retry_ms(), Some(5000));
+}
+
+#[test]
+fn test_sse_parser_split_chunks_and_line_endings() {
+    let mut parser = SseParser::new();
+    let stream = "\u{feff}data: caf\u{e9}\r\n\r\ndata: two\r\rdata: three\n\n".as_bytes();
+    
+    let mut events = Vec::new();
+    for byte in stream {
+        events.extend(parser.feed(std::slice::from_ref(byte)));
+    }
+    
+    let data: Vec<_> = events.iter().map(|event| event.data.as_str()).collect();
+    assert_eq!(data, vec!["caf\u{e9}", "two", "three"]);
+}
+
+#[test]
+fn test_sse_parser_ignores_invalid_fields() {
+    let mut parser = SseParser::with_last_event_id(Some("3".to_string()));
+    let events = parser.feed(b"retry: soon\nid: bad\0id\nevent: ping\n\ndata\n\n");
+    
+    // An event without data is not dispatched but its type is reset
+    assert_eq!(events, vec![SseEvent { event: "message".to_string(), data: String::new(), id: Some("3".to_string()) }]);
+    assert_eq!(parser.retry_ms(), None);
+    
+    parser.feed(b"id\n\n");
+    assert_eq!(parser.last_event_id(), None);
+}
+
+#[test]
+fn test_sse_parser_reset_discards_partial_event() {
+    let mut parser = SseParser::new();
+    assert!(parser.feed(b"id: 1\ndata: partial\n").is_empty());
+    
+    parser.reset();
+    assert_eq!(parser.feed(b"data: next\n\n")[0].data, "next");
+    assert_eq!(parser.last_event_id(), Some("1"));
+}
+
+#[test]
+fn test_sse_event_json() {
+    #[derive(Deserialize)]
+    struct Price {
+        symbol: String,
+        value: f64,
+    }
+    
+    let event = SseEvent { event: "price".to_string(), data: r#"{"symbol":"ACME","value":12.5}"#.to_string(), id: None };
+    let price: Price = event.json().unwrap();
+    assert_eq!(price.symbol, "ACME");
+    assert_eq!(price.value, 12.5);
+}
+
+#[test]
+fn test_sse_transport_selection() {
+    let client = HttpClient::new().base_url("https://api.example.com");
+    
+    assert_eq!(client.sse("/events").resolved_transport(), SseTransport::EventSource);
+    assert_eq!(
+        client.sse("/events").header("Authorization", "Bearer token").resolved_transport(),
+        SseTransport::Fetch
+    );
+    assert_eq!(
+        client.sse("/events").transport(SseTransport::Fetch).resolved_transport(),
+        SseTransport::Fetch
+    );
+    
+    let authed = client.default_header("Authorization", "Bearer token");
+    assert_eq!(authed.sse("/events").resolved_transport(), SseTransport::Fetch);
+}
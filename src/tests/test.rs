@@ -269,4 +269,322 @@ fn test_json_serialization_error() {
         },
         _ => panic!("Expected serialization error"),
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_global_error_handler_receives_final_error() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let client = {
+        let seen = seen.clone();
+        HttpClient::new().on_error(move |error, config| {
+            seen.borrow_mut().push((error.clone(), config.call_name.clone()));
+        })
+    };
+    
+    let result = futures::executor::block_on(client.get("").call_name("load_me").send());
+    assert!(matches!(result, Err(HttpError::InvalidUrl { .. })));
+    
+    let seen = seen.borrow();
+    assert_eq!(seen.len(), 1);
+    assert!(matches!(seen[0].0, HttpError::InvalidUrl { .. }));
+    assert_eq!(seen[0].1, Some("load_me".to_string()));
+}
+
+#[test]
+fn test_error_handler_can_be_skipped_per_request() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    
+    let calls = Rc::new(Cell::new(0));
+    let client = {
+        let calls = calls.clone();
+        HttpClient::new().on_error(move |_, _| calls.set(calls.get() + 1))
+    };
+    
+    let result = futures::executor::block_on(client.get("").without_error_handler().send());
+    assert!(result.is_err());
+    assert_eq!(calls.get(), 0);
+}
+
+#[test]
+fn test_deduplication_key() {
+    let client = HttpClient::new().deduplicate(true);
+    
+    let first = client.get("/api/me").header("Accept", "application/json");
+    let second = client.get("/api/me").header("Accept", "application/json");
+    assert!(first.dedupe_key().is_some());
+    assert_eq!(first.dedupe_key(), second.dedupe_key());
+    
+    let other_headers = client.get("/api/me").header("Accept", "text/plain");
+    assert_ne!(first.dedupe_key(), other_headers.dedupe_key());
+    
+    // Equally configured clients share the key, whatever their error handler
+    let other_client = HttpClient::new().deduplicate(true).get("/api/me").header("Accept", "application/json");
+    assert_eq!(first.dedupe_key(), other_client.dedupe_key());
+    let handled = client.clone().on_error(|_, _| {}).get("/api/me").header("Accept", "application/json");
+    assert_eq!(first.dedupe_key(), handled.dedupe_key());
+    
+    // Differences in validation, throttling or loader state keep requests apart
+    let restricted = client.clone().allowed_origins(["https://api.example.com"]).get("/api/me").header("Accept", "application/json");
+    assert_ne!(first.dedupe_key(), restricted.dedupe_key());
+    let scheduled = client.clone().max_in_flight(2).get("/api/me").header("Accept", "application/json");
+    assert_ne!(first.dedupe_key(), scheduled.dedupe_key());
+    let loading = client.get("/api/me").header("Accept", "application/json").with_loader(true);
+    assert_ne!(first.dedupe_key(), loading.dedupe_key());
+    
+    // Only safe methods are coalesced, and only when enabled
+    assert!(client.post("/api/me").dedupe_key().is_none());
+    assert!(HttpClient::new().get("/api/me").dedupe_key().is_none());
+    assert!(client.get("/api/me").deduplicate(false).dedupe_key().is_none());
+}
+
+#[test]
+fn test_deduplicated_requests_share_result() {
+    let client = HttpClient::new().deduplicate(true);
+    
+    let (first, second) = futures::executor::block_on(futures::future::join(
+        client.get("").call_name("first").send(),
+        client.get("").call_name("second").send(),
+    ));
+    
+    assert!(matches!(first, Err(HttpError::InvalidUrl { .. })));
+    assert_eq!(first.unwrap_err(), second.unwrap_err());
+    assert!(crate::IN_FLIGHT.with(|in_flight| in_flight.borrow().is_empty()));
+}
+
+#[test]
+fn test_coalesced_requests_fetch_once() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use futures::future::FutureExt;
+    
+    let fetches = Rc::new(Cell::new(0));
+    let start = || {
+        let fetches = fetches.clone();
+        move || {
+            fetches.set(fetches.get() + 1);
+            async {
+                yield_once().await;
+                Err(HttpError::Timeout)
+            }
+            .boxed_local()
+        }
+    };
+    
+    let first = crate::coalesce("GET /api/me".to_string(), None, start());
+    let second = crate::coalesce("GET /api/me".to_string(), None, start());
+    let (first, second) = futures::executor::block_on(futures::future::join(first, second));
+    
+    assert_eq!(fetches.get(), 1);
+    assert_eq!(first.unwrap_err(), HttpError::Timeout);
+    assert_eq!(second.unwrap_err(), HttpError::Timeout);
+    assert!(crate::IN_FLIGHT.with(|in_flight| in_flight.borrow().is_empty()));
+}
+
+#[test]
+fn test_coalesced_error_reported_once() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    
+    use crate::{Priority, RequestScheduler};
+    
+    let calls = Rc::new(Cell::new(0));
+    let scheduler = RequestScheduler::new(1);
+    let client = {
+        let calls = calls.clone();
+        HttpClient::new()
+            .deduplicate(true)
+            .scheduler(scheduler.clone())
+            .on_error(move |_, _| calls.set(calls.get() + 1))
+    };
+    
+    // Hold the only slot so the first request is still in flight when the second starts
+    let (first, second, _) = futures::executor::block_on(async {
+        let permit = scheduler.acquire(Priority::Normal).await;
+        futures::future::join3(
+            client.get("").send(),
+            client.get("").send(),
+            async move {
+                yield_once().await;
+                drop(permit);
+            },
+        )
+        .await
+    });
+    
+    assert!(first.is_err() && second.is_err());
+    assert_eq!(calls.get(), 1);
+}
+
+#[test]
+fn test_coalesced_across_dispatcher_clients() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use yew::Callback;
+    
+    use crate::{Priority, RequestScheduler};
+    
+    let scheduler = RequestScheduler::new(1);
+    let calls = Rc::new(Cell::new(0));
+    // Two components calling use_http_client() each get their own client
+    let component_client = |calls: Rc<Cell<u32>>| {
+        HttpClient::with_dispatcher(Callback::from(|_| ()))
+            .deduplicate(true)
+            .scheduler(scheduler.clone())
+            .on_error(move |_, _| calls.set(calls.get() + 1))
+    };
+    let first_client = component_client(calls.clone());
+    let second_client = component_client(calls.clone());
+    
+    let (first, second, _) = futures::executor::block_on(async {
+        let permit = scheduler.acquire(Priority::Normal).await;
+        futures::future::join3(
+            first_client.get("").send(),
+            second_client.get("").send(),
+            async {
+                yield_once().await;
+                // Only the shared request is waiting for the slot
+                assert_eq!(scheduler.queued(), 1);
+                drop(permit);
+            },
+        )
+        .await
+    });
+    
+    assert!(first.is_err() && second.is_err());
+    // Each client's own handler hears about the failure once
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn test_idempotency_key_generated_once() {
+    let client = HttpClient::new();
+    
+    let builder = client.post("/api/orders").idempotent();
+    let key = builder.config.idempotency_key.clone().expect("key generated");
+    assert_eq!(key.len(), 36);
+    assert_eq!(&key[14..15], "4");
+    
+    // Calling idempotent() again keeps the same key
+    let builder = builder.idempotent();
+    assert_eq!(builder.config.idempotency_key, Some(key.clone()));
+    
+    // Every request gets its own key
+    let other = client.post("/api/orders").idempotent();
+    assert_ne!(other.config.idempotency_key, Some(key));
+}
+
+#[test]
+fn test_retry_policy_respects_idempotency() {
+    let client = HttpClient::new();
+    
+    assert!(client.get("/api/orders").is_retryable());
+    assert!(client.put("/api/orders/1").is_retryable());
+    assert!(!client.post("/api/orders").is_retryable());
+    assert!(!client.patch("/api/orders/1").is_retryable());
+    
+    assert!(client.post("/api/orders").idempotent().is_retryable());
+    assert!(client.patch("/api/orders/1").idempotency_key("order-1-update").is_retryable());
+}
+
+#[test]
+fn test_hedge_configuration() {
+    use crate::HedgePolicy;
+    
+    let client = HttpClient::new();
+    let builder = client.get("/api/search").hedge(200, 2);
+    
+    assert_eq!(builder.config.hedge, Some(HedgePolicy { after_ms: 200, max_extra: 2 }));
+    assert!(builder.is_retryable());
+    assert_eq!(client.get("/api/search").config.hedge, None);
+}
+
+/// Return `Pending` once, waking immediately, so other futures get polled first
+pub(super) async fn yield_once() {
+    let mut yielded = false;
+    futures::future::poll_fn(|cx| {
+        if yielded {
+            std::task::Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    })
+    .await
+}
+
+#[test]
+fn test_hedge_first_success_wins_over_earlier_failure() {
+    use crate::first_success;
+    use futures::future::{self, FutureExt};
+    
+    let attempts = vec![
+        future::ready(Err::<u32, &str>("fast failure")).boxed_local(),
+        async {
+            yield_once().await;
+            Ok(2)
+        }
+        .boxed_local(),
+        future::pending().boxed_local(),
+    ];
+    
+    assert_eq!(futures::executor::block_on(first_success(attempts, |_| false)), Ok(2));
+}
+
+#[test]
+fn test_hedge_fails_only_when_all_attempts_fail() {
+    use crate::first_success;
+    use futures::future::{self, FutureExt};
+    
+    let attempts = vec![
+        future::ready(Err::<u32, &str>("first")).boxed_local(),
+        async {
+            yield_once().await;
+            Err("second")
+        }
+        .boxed_local(),
+    ];
+    
+    assert_eq!(futures::executor::block_on(first_success(attempts, |_| false)), Err("second"));
+}
+
+#[test]
+fn test_hedge_client_error_ends_race() {
+    use crate::{first_success, is_definitive};
+    use futures::future::{self, FutureExt};
+    use std::cell::Cell;
+    
+    let started = Cell::new(false);
+    let not_found = HttpError::Http { status: 404, message: "Not Found".to_string(), body: None };
+    let attempts = vec![
+        future::ready(Err::<u32, _>(not_found)).boxed_local(),
+        async {
+            // Stands in for the hedge delay
+            yield_once().await;
+            started.set(true);
+            Ok(2)
+        }
+        .boxed_local(),
+    ];
+    
+    let result = futures::executor::block_on(first_success(attempts, is_definitive));
+    assert!(matches!(result, Err(HttpError::Http { status: 404, .. })));
+    assert!(!started.get());
+}
+
+#[test]
+fn test_response_text_is_derived_from_bytes() {
+    use crate::HttpResponse;
+    
+    let response = HttpResponse::new(200, "/api/me", br#"{"name":"Ada"}"#.to_vec());
+    assert_eq!(response.text(), r#"{"name":"Ada"}"#);
+    assert_eq!(response.json::<serde_json::Value>().unwrap()["name"], "Ada");
+    
+    let response = HttpResponse::new(200, "/api/raw", vec![b'o', b'k', 0xff]);
+    assert_eq!(response.text(), "ok\u{fffd}");
+    assert_eq!(response.bytes(), &[b'o', b'k', 0xff]);
+}
@@ -0,0 +1,78 @@
+use crate::tus::resolve_location;
+use crate::{HttpClient, HttpError, HttpMethod, MemoryUrlStore, TusUrlStore};
+
+#[test]
+fn test_tus_metadata_header() {
+    let upload = HttpClient::new()
+        .tus_upload("/files", b"hello".to_vec())
+        .metadata("filename", "report.pdf")
+        .metadata("is_confidential", "");
+
+    assert_eq!(
+        upload.metadata_header(),
+        Some("filename cmVwb3J0LnBkZg==,is_confidential".to_string())
+    );
+    assert_eq!(HttpClient::new().tus_upload("/files", Vec::new()).metadata_header(), None);
+}
+
+#[test]
+fn test_tus_resolve_location() {
+    assert_eq!(
+        resolve_location("https://tus.example.com/files/", "/files/abc"),
+        "https://tus.example.com/files/abc"
+    );
+    assert_eq!(
+        resolve_location("https://tus.example.com/files/", "abc"),
+        "https://tus.example.com/files/abc"
+    );
+    assert_eq!(
+        resolve_location("https://tus.example.com/files", "https://cdn.example.com/uploads/abc"),
+        "https://cdn.example.com/uploads/abc"
+    );
+    assert_eq!(resolve_location("/api/files/", "abc"), "/api/files/abc");
+}
+
+#[test]
+fn test_tus_memory_url_store_is_shared() {
+    let store = MemoryUrlStore::new();
+    let clone = store.clone();
+
+    store.set("report.pdf-1024", "https://tus.example.com/files/abc");
+    assert_eq!(clone.get("report.pdf-1024"), Some("https://tus.example.com/files/abc".to_string()));
+
+    clone.remove("report.pdf-1024");
+    assert_eq!(store.get("report.pdf-1024"), None);
+}
+
+#[test]
+fn test_tus_urls_are_resolved_once() {
+    let client = HttpClient::new().base_url("/api");
+    let upload = client.tus_upload("/files/", b"hello".to_vec());
+    assert_eq!(upload.endpoint(), "/api/files/");
+
+    // Requests to resolved tus URLs must not join the base URL again
+    let upload_url = resolve_location(upload.endpoint(), "abc");
+    assert_eq!(upload_url, "/api/files/abc");
+    assert_eq!(client.builder_for_url(HttpMethod::Patch, &upload_url).config.url, "/api/files/abc");
+}
+
+#[test]
+fn test_tus_reports_only_the_upload_outcome() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let reported = Rc::new(RefCell::new(Vec::new()));
+    let client = {
+        let reported = reported.clone();
+        HttpClient::new().on_error(move |error, config| reported.borrow_mut().push((error.clone(), config.call_name.clone())))
+    };
+
+    // The creation POST fails validation before reaching the network
+    let result = futures::executor::block_on(client.tus_upload("", b"hello".to_vec()).start());
+    assert!(matches!(result, Err(HttpError::InvalidUrl { .. })));
+
+    let reported = reported.borrow();
+    assert_eq!(reported.len(), 1);
+    assert!(matches!(reported[0].0, HttpError::InvalidUrl { .. }));
+    assert_eq!(reported[0].1.as_deref(), Some("tus_upload"));
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use crate::ws::{backoff_delay, websocket_url};
+use crate::{HttpError, WsMessage};
+
+#[test]
+fn test_websocket_url_scheme_conversion() {
+    assert_eq!(websocket_url("https://api.example.com/v1/live", None, &[]).unwrap(), "wss://api.example.com/v1/live");
+    assert_eq!(websocket_url("http://localhost:8080/ws?room=1", None, &[]).unwrap(), "ws://localhost:8080/ws?room=1");
+    assert_eq!(websocket_url("wss://push.example.com/", None, &[]).unwrap(), "wss://push.example.com/");
+}
+
+#[test]
+fn test_websocket_url_relative_to_page() {
+    assert_eq!(
+        websocket_url("/live", Some("https://app.example.com/dashboard"), &[]).unwrap(),
+        "wss://app.example.com/live"
+    );
+    assert!(matches!(websocket_url("/live", None, &[]), Err(HttpError::InvalidUrl { .. })));
+}
+
+#[test]
+fn test_websocket_url_checks_allowed_origins() {
+    let allowed = vec!["https://api.example.com".to_string()];
+    
+    assert!(websocket_url("wss://api.example.com/live", None, &allowed).is_ok());
+    assert!(matches!(
+        websocket_url("wss://evil.example.com/live", None, &allowed),
+        Err(HttpError::InvalidUrl { .. })
+    ));
+    assert!(matches!(websocket_url("ftp://api.example.com/live", None, &[]), Err(HttpError::InvalidUrl { .. })));
+}
+
+#[test]
+fn test_backoff_delay() {
+    assert_eq!(backoff_delay(1, 500, 30_000), 500);
+    assert_eq!(backoff_delay(2, 500, 30_000), 1000);
+    assert_eq!(backoff_delay(4, 500, 30_000), 4000);
+    assert_eq!(backoff_delay(10, 500, 30_000), 30_000);
+    assert_eq!(backoff_delay(64, 500, 30_000), 30_000);
+}
+
+#[test]
+fn test_ws_message_json() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Chat {
+        room: String,
+        text: String,
+    }
+    
+    let chat = Chat { room: "general".to_string(), text: "hi".to_string() };
+    let text = WsMessage::Text(serde_json::to_string(&chat).unwrap());
+    let binary = WsMessage::Binary(serde_json::to_vec(&chat).unwrap());
+    
+    assert_eq!(text.json::<Chat>().unwrap(), chat);
+    assert_eq!(binary.json::<Chat>().unwrap(), chat);
+    assert!(matches!(WsMessage::Text("nope".to_string()).json::<Chat>(), Err(HttpError::Serialization { .. })));
+}
@@ -0,0 +1,406 @@
+//! Resumable uploads via the tus 1.0 protocol
+//!
+//! A [`TusUpload`] creates an upload on the server (`POST`), sends the data in
+//! chunks (`PATCH`) and, when a chunk fails, asks the server for the offset it
+//! actually received (`HEAD`) before continuing from there. Upload URLs can be
+//! persisted in a [`TusUrlStore`] so an upload interrupted by a page reload
+//! resumes instead of starting from zero. Progress is reported through the
+//! client's dispatcher. `Blob`/`File` sources are read chunk by chunk, so large
+//! files are never loaded into memory at once.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use base64::Engine;
+use httpmessenger::AppAction;
+use url::Url;
+use crate::presigned::UploadSource;
+use crate::{HttpClient, HttpError, HttpMethod, HttpResponse, RequestBuilder};
+
+/// Protocol version sent in the `Tus-Resumable` header
+pub const TUS_VERSION: &str = "1.0.0";
+
+/// Default chunk size (5 MiB)
+const DEFAULT_CHUNK_SIZE: usize = 5 * 1024 * 1024;
+
+/// Storage for upload URLs, keyed by an upload fingerprint
+pub trait TusUrlStore {
+    fn get(&self, fingerprint: &str) -> Option<String>;
+    fn set(&self, fingerprint: &str, upload_url: &str);
+    fn remove(&self, fingerprint: &str);
+}
+
+/// Upload URL store backed by the browser's `localStorage`
+#[derive(Debug, Clone)]
+pub struct LocalStorageUrlStore {
+    prefix: String,
+}
+
+impl LocalStorageUrlStore {
+    /// Store URLs under keys starting with `prefix`
+    pub fn new(prefix: &str) -> Self {
+        Self { prefix: prefix.to_string() }
+    }
+
+    fn storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn key(&self, fingerprint: &str) -> String {
+        format!("{}{}", self.prefix, fingerprint)
+    }
+}
+
+impl Default for LocalStorageUrlStore {
+    fn default() -> Self {
+        Self::new("httpcalls::tus::")
+    }
+}
+
+impl TusUrlStore for LocalStorageUrlStore {
+    fn get(&self, fingerprint: &str) -> Option<String> {
+        Self::storage()?.get_item(&self.key(fingerprint)).ok()?
+    }
+
+    fn set(&self, fingerprint: &str, upload_url: &str) {
+        if let Some(storage) = Self::storage() {
+            let _ = storage.set_item(&self.key(fingerprint), upload_url);
+        }
+    }
+
+    fn remove(&self, fingerprint: &str) {
+        if let Some(storage) = Self::storage() {
+            let _ = storage.remove_item(&self.key(fingerprint));
+        }
+    }
+}
+
+/// In-memory upload URL store, shared by all clones
+#[derive(Debug, Clone, Default)]
+pub struct MemoryUrlStore {
+    urls: Rc<RefCell<HashMap<String, String>>>,
+}
+
+impl MemoryUrlStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TusUrlStore for MemoryUrlStore {
+    fn get(&self, fingerprint: &str) -> Option<String> {
+        self.urls.borrow().get(fingerprint).cloned()
+    }
+
+    fn set(&self, fingerprint: &str, upload_url: &str) {
+        self.urls.borrow_mut().insert(fingerprint.to_string(), upload_url.to_string());
+    }
+
+    fn remove(&self, fingerprint: &str) {
+        self.urls.borrow_mut().remove(fingerprint);
+    }
+}
+
+/// Resumable tus upload
+#[derive(Clone)]
+pub struct TusUpload {
+    client: HttpClient,
+    /// Creation endpoint, already resolved against the client's base URL
+    endpoint: String,
+    source: UploadSource,
+    chunk_size: usize,
+    metadata: Vec<(String, String)>,
+    fingerprint: Option<String>,
+    store: Option<Rc<dyn TusUrlStore>>,
+    retry_delays_ms: Vec<u32>,
+    upload_url: Option<String>,
+}
+
+impl TusUpload {
+    /// Prepare an upload of `data` to the tus creation `endpoint`
+    pub fn new(client: &HttpClient, endpoint: &str, data: Vec<u8>) -> Self {
+        Self::with_source(client, endpoint, UploadSource::Bytes(Rc::new(data)))
+    }
+
+    /// Prepare an upload of a `Blob` or `File`, read chunk by chunk
+    pub fn from_blob(client: &HttpClient, endpoint: &str, blob: web_sys::Blob) -> Self {
+        Self::with_source(client, endpoint, UploadSource::Blob(blob))
+    }
+
+    fn with_source(client: &HttpClient, endpoint: &str, source: UploadSource) -> Self {
+        Self {
+            client: client.clone(),
+            endpoint: client.build_url(endpoint),
+            source,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            metadata: Vec::new(),
+            fingerprint: None,
+            store: None,
+            retry_delays_ms: vec![0, 1000, 3000, 5000],
+            upload_url: None,
+        }
+    }
+
+    /// Creation endpoint, resolved against the client's base URL
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Set the size of each `PATCH` request in bytes
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Add an `Upload-Metadata` entry (e.g. `filename`, `filetype`)
+    pub fn metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Persist the upload URL in `store` so the upload can resume after a reload
+    ///
+    /// The fingerprint identifies the file, e.g. its name, size and modification time.
+    pub fn resumable(mut self, fingerprint: &str, store: impl TusUrlStore + 'static) -> Self {
+        self.fingerprint = Some(fingerprint.to_string());
+        self.store = Some(Rc::new(store));
+        self
+    }
+
+    /// Delays between attempts after a failed chunk; the length sets the number of retries
+    pub fn retry_delays(mut self, delays_ms: Vec<u32>) -> Self {
+        self.retry_delays_ms = delays_ms;
+        self
+    }
+
+    /// Resume an upload whose URL is already known
+    ///
+    /// The URL is used as-is, like the one returned by [`start`](Self::start).
+    pub fn upload_url(mut self, url: &str) -> Self {
+        self.upload_url = Some(url.to_string());
+        self
+    }
+
+    /// `Upload-Metadata` header value
+    pub fn metadata_header(&self) -> Option<String> {
+        if self.metadata.is_empty() {
+            return None;
+        }
+
+        let pairs: Vec<String> = self.metadata
+            .iter()
+            .map(|(key, value)| {
+                if value.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{} {}", key, base64::engine::general_purpose::STANDARD.encode(value))
+                }
+            })
+            .collect();
+        Some(pairs.join(","))
+    }
+
+    /// Upload all data, resuming from the server's offset after failures
+    ///
+    /// Returns the upload URL once the server has received every byte. Only
+    /// this final outcome reaches the client's error handler; failed chunks
+    /// that are resumed and a forgotten stored upload are not reported.
+    pub async fn start(self) -> Result<String, HttpError> {
+        if let Some(ref dispatch) = self.client.dispatch {
+            dispatch.emit(AppAction::EnableLoader);
+        }
+
+        let result = self.run().await;
+
+        if let Some(ref dispatch) = self.client.dispatch {
+            dispatch.emit(AppAction::DisableLoader);
+        }
+
+        self.client
+            .builder_for_url(HttpMethod::Post, &self.endpoint)
+            .call_name("tus_upload")
+            .report_outcome(&result);
+        result
+    }
+
+    async fn run(&self) -> Result<String, HttpError> {
+        let (upload_url, mut offset) = self.find_or_create().await?;
+        let total = self.source.len();
+        self.report_progress(offset);
+
+        let mut failures = 0;
+        while offset < total {
+            match self.send_chunk(&upload_url, offset).await {
+                // The server accepted the PATCH without taking any data
+                Ok(new_offset) if new_offset <= offset || new_offset > total => {
+                    return Err(HttpError::Network {
+                        message: format!("tus upload stalled: server reported offset {} after PATCH at {}", new_offset, offset),
+                    });
+                }
+                Ok(new_offset) => {
+                    failures = 0;
+                    offset = new_offset;
+                    self.report_progress(offset);
+                }
+                Err(error) => {
+                    if !is_recoverable(&error) || failures >= self.retry_delays_ms.len() {
+                        return Err(error);
+                    }
+
+                    let delay = self.retry_delays_ms[failures];
+                    failures += 1;
+                    if delay > 0 {
+                        gloo_timers::future::TimeoutFuture::new(delay).await;
+                    }
+
+                    // Continue from what the server actually received
+                    if let Ok(server_offset) = self.query_offset(&upload_url).await {
+                        offset = server_offset;
+                    }
+                }
+            }
+        }
+
+        if let (Some(fingerprint), Some(store)) = (&self.fingerprint, &self.store) {
+            store.remove(fingerprint);
+        }
+
+        Ok(upload_url)
+    }
+
+    /// Resume a known upload or create a new one, returning its URL and offset
+    async fn find_or_create(&self) -> Result<(String, usize), HttpError> {
+        let known = self.upload_url.clone().or_else(|| {
+            let fingerprint = self.fingerprint.as_ref()?;
+            self.store.as_ref()?.get(fingerprint)
+        });
+
+        if let Some(upload_url) = known {
+            match self.query_offset(&upload_url).await {
+                Ok(offset) => return Ok((upload_url, offset)),
+                // The server forgot the upload: start over
+                Err(HttpError::Http { status: 403 | 404 | 410, .. }) => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        let upload_url = self.create().await?;
+        if let (Some(fingerprint), Some(store)) = (&self.fingerprint, &self.store) {
+            store.set(fingerprint, &upload_url);
+        }
+        Ok((upload_url, 0))
+    }
+
+    /// Create the upload on the server (`POST`)
+    async fn create(&self) -> Result<String, HttpError> {
+        let mut request = self.tus_request(HttpMethod::Post, &self.endpoint)
+            .header("Upload-Length", &self.source.len().to_string());
+        if let Some(metadata) = self.metadata_header() {
+            request = request.header("Upload-Metadata", &metadata);
+        }
+
+        let response = request.send().await?;
+        let location = response.header("Location").ok_or(HttpError::InvalidResponse)?;
+        Ok(resolve_location(&self.endpoint, location))
+    }
+
+    /// Ask the server how many bytes it has received (`HEAD`)
+    async fn query_offset(&self, upload_url: &str) -> Result<usize, HttpError> {
+        let response = self.tus_request(HttpMethod::Head, upload_url)
+            .header("Cache-Control", "no-store")
+            .send()
+            .await?;
+        upload_offset(&response)
+    }
+
+    /// Send the chunk starting at `offset` (`PATCH`), returning the new offset
+    async fn send_chunk(&self, upload_url: &str, offset: usize) -> Result<usize, HttpError> {
+        let end = (offset + self.chunk_size).min(self.source.len());
+        let chunk = self.source.read(offset, end).await?;
+
+        let response = self.tus_request(HttpMethod::Patch, upload_url)
+            .header("Upload-Offset", &offset.to_string())
+            .header("Content-Type", "application/offset+octet-stream")
+            .binary(chunk)
+            .send()
+            .await?;
+        upload_offset(&response)
+    }
+
+    /// Delete an unfinished upload on the server (termination extension)
+    ///
+    /// `upload_url` is used as-is, like the one returned by [`start`](Self::start).
+    pub async fn terminate(client: &HttpClient, upload_url: &str) -> Result<(), HttpError> {
+        client
+            .builder_for_url(HttpMethod::Delete, upload_url)
+            .header("Tus-Resumable", TUS_VERSION)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Request to an already resolved tus URL
+    ///
+    /// Errors are left to [`start`](Self::start), which reports the outcome
+    /// of the whole upload.
+    fn tus_request(&self, method: HttpMethod, url: &str) -> RequestBuilder {
+        // Chunks are retried from the server offset here, not by the builder
+        self.client
+            .builder_for_url(method, url)
+            .header("Tus-Resumable", TUS_VERSION)
+            .retry(0, 0)
+            .without_error_handler()
+    }
+
+    fn report_progress(&self, offset: usize) {
+        if let Some(ref dispatch) = self.client.dispatch {
+            let total = self.source.len().max(1);
+            dispatch.emit(AppAction::UpdateProgress(offset as f64 / total as f64));
+        }
+    }
+}
+
+impl HttpClient {
+    /// Prepare a resumable tus upload of `data` to the creation `endpoint`
+    pub fn tus_upload(&self, endpoint: &str, data: Vec<u8>) -> TusUpload {
+        TusUpload::new(self, endpoint, data)
+    }
+
+    /// Prepare a resumable tus upload of a `Blob` or `File` to the creation `endpoint`
+    pub fn tus_upload_blob(&self, endpoint: &str, blob: web_sys::Blob) -> TusUpload {
+        TusUpload::from_blob(self, endpoint, blob)
+    }
+}
+
+/// Whether a failed chunk can be resumed from the server offset
+fn is_recoverable(error: &HttpError) -> bool {
+    match error {
+        HttpError::Network { .. } | HttpError::Timeout | HttpError::InvalidResponse => true,
+        // 409 Conflict: offset mismatch, 423 Locked: previous request still being processed
+        HttpError::Http { status, .. } => *status >= 500 || matches!(*status, 409 | 423 | 429),
+        _ => false,
+    }
+}
+
+/// Parse the `Upload-Offset` response header
+fn upload_offset(response: &HttpResponse) -> Result<usize, HttpError> {
+    response
+        .header("Upload-Offset")
+        .and_then(|offset| offset.trim().parse().ok())
+        .ok_or(HttpError::InvalidResponse)
+}
+
+/// Resolve a `Location` header against the creation endpoint
+pub fn resolve_location(endpoint: &str, location: &str) -> String {
+    if let Ok(endpoint) = Url::parse(endpoint) {
+        return endpoint.join(location).map(String::from).unwrap_or_else(|_| location.to_string());
+    }
+
+    // Relative endpoint: resolve against a placeholder origin and keep the path
+    let placeholder = Url::parse("http://relative.invalid/").expect("valid placeholder origin");
+    match placeholder.join(endpoint).and_then(|endpoint| endpoint.join(location)) {
+        Ok(url) if url.host_str() == Some("relative.invalid") => url[url::Position::BeforePath..].to_string(),
+        Ok(url) => url.to_string(),
+        Err(_) => location.to_string(),
+    }
+}
@@ -0,0 +1,563 @@
+//! WebSocket client with reconnect and typed JSON messages
+//!
+//! A [`WsClient`] is created from an [`HttpClient`] and resolves its URL
+//! against the same base URL, switching `http`/`https` to `ws`/`wss`. Dropped
+//! connections are re-established with exponential backoff; messages sent
+//! while disconnected are queued and flushed once the socket opens again.
+//! Connection status can be published through the httpmessenger dispatcher
+//! (loader while connecting, notifications when the connection drops).
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::stream::{LocalBoxStream, Stream, StreamExt};
+use httpmessenger::{AppAction, StoreDispatcher};
+use serde::{de::DeserializeOwned, Serialize};
+use url::Url;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use yew::Callback;
+use crate::{random, resolve, HttpClient, HttpError};
+
+/// Default maximum number of messages queued while disconnected
+const DEFAULT_MAX_QUEUE: usize = 1000;
+
+/// A WebSocket message
+#[derive(Debug, Clone, PartialEq)]
+pub enum WsMessage {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl WsMessage {
+    /// Parse the message as JSON
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        let result = match self {
+            WsMessage::Text(text) => serde_json::from_str(text),
+            WsMessage::Binary(data) => serde_json::from_slice(data),
+        };
+        result.map_err(|e| HttpError::Serialization {
+            message: format!("Failed to deserialize WebSocket message: {}", e),
+        })
+    }
+}
+
+/// State of a WebSocket connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WsState {
+    Connecting,
+    Open,
+    /// The connection dropped and a new attempt is scheduled
+    Reconnecting,
+    Closed,
+}
+
+/// Convert a resolved HTTP(S) or WebSocket URL to a `ws`/`wss` URL
+///
+/// Relative URLs are resolved against `page_url`, the page the app runs on.
+/// `allowed_origins` is checked with the equivalent HTTP origin.
+pub fn websocket_url(url: &str, page_url: Option<&str>, allowed_origins: &[String]) -> Result<String, HttpError> {
+    let invalid = |reason: String| HttpError::InvalidUrl {
+        url: url.to_string(),
+        reason,
+    };
+
+    let mut parsed = match (Url::parse(url), page_url) {
+        (Ok(parsed), _) => parsed,
+        (Err(url::ParseError::RelativeUrlWithoutBase), Some(page_url)) => Url::parse(page_url)
+            .and_then(|page| page.join(url))
+            .map_err(|e| invalid(e.to_string()))?,
+        (Err(e), _) => return Err(invalid(e.to_string())),
+    };
+
+    let (http_scheme, ws_scheme) = match parsed.scheme() {
+        "http" | "ws" => ("http", "ws"),
+        "https" | "wss" => ("https", "wss"),
+        scheme => return Err(invalid(format!("unsupported scheme `{}`", scheme))),
+    };
+
+    parsed.set_scheme(http_scheme).map_err(|_| invalid("cannot change scheme".to_string()))?;
+    resolve::validate_url(parsed.as_str(), allowed_origins)?;
+
+    parsed.set_scheme(ws_scheme).map_err(|_| invalid("cannot change scheme".to_string()))?;
+    parsed.set_fragment(None);
+    Ok(parsed.to_string())
+}
+
+/// Delay before reconnect attempt `attempt` (starting at 1), without jitter
+pub fn backoff_delay(attempt: u32, initial_ms: u32, max_ms: u32) -> u32 {
+    let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+    initial_ms.saturating_mul(factor).min(max_ms)
+}
+
+/// The socket and the handlers keeping its callbacks alive
+struct SocketHandle {
+    socket: web_sys::WebSocket,
+    _on_open: Closure<dyn FnMut(web_sys::Event)>,
+    _on_message: Closure<dyn FnMut(web_sys::MessageEvent)>,
+    _on_close: Closure<dyn FnMut(web_sys::CloseEvent)>,
+}
+
+impl SocketHandle {
+    fn detach(&self) {
+        self.socket.set_onopen(None);
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
+
+struct Heartbeat {
+    interval_ms: u32,
+    message: WsMessage,
+}
+
+struct WsShared {
+    url: String,
+    protocols: Vec<String>,
+    socket: RefCell<Option<SocketHandle>>,
+    state: Cell<WsState>,
+    closed: Cell<bool>,
+    attempts: Cell<u32>,
+    queue: RefCell<VecDeque<WsMessage>>,
+    max_queue: usize,
+    subscribers: RefCell<Vec<UnboundedSender<WsMessage>>>,
+    initial_backoff_ms: u32,
+    max_backoff_ms: u32,
+    max_retries: Option<u32>,
+    heartbeat: Option<Heartbeat>,
+    heartbeat_timer: RefCell<Option<gloo_timers::callback::Interval>>,
+    dispatch: Option<StoreDispatcher>,
+    with_loader: bool,
+    with_notifications: bool,
+    on_state_change: Option<Callback<WsState>>,
+}
+
+impl WsShared {
+    fn set_state(&self, state: WsState) {
+        let previous = self.state.replace(state);
+        if previous == state {
+            return;
+        }
+
+        if let Some(ref dispatch) = self.dispatch {
+            if self.with_loader {
+                let connecting = |state| matches!(state, WsState::Connecting | WsState::Reconnecting);
+                if connecting(state) && !connecting(previous) {
+                    dispatch.emit(AppAction::EnableLoader);
+                } else if !connecting(state) && connecting(previous) {
+                    dispatch.emit(AppAction::DisableLoader);
+                }
+            }
+
+            if self.with_notifications {
+                let message = match (previous, state) {
+                    (WsState::Open, WsState::Reconnecting) => Some("Connection lost, reconnecting..."),
+                    (WsState::Reconnecting, WsState::Open) => Some("Connection restored"),
+                    (_, WsState::Closed) if !self.closed.get() => Some("Connection closed"),
+                    _ => None,
+                };
+                if let Some(message) = message {
+                    dispatch.emit(AppAction::ShowNotification(message.to_string()));
+                }
+            }
+        }
+
+        if let Some(ref handler) = self.on_state_change {
+            handler.emit(state);
+        }
+    }
+
+    fn send_now(&self, message: &WsMessage) -> Result<(), HttpError> {
+        let socket = self.socket.borrow();
+        let socket = socket
+            .as_ref()
+            .filter(|handle| handle.socket.ready_state() == web_sys::WebSocket::OPEN)
+            .ok_or_else(|| HttpError::Network {
+                message: "WebSocket is not open".to_string(),
+            })?;
+
+        let result = match message {
+            WsMessage::Text(text) => socket.socket.send_with_str(text),
+            WsMessage::Binary(data) => socket.socket.send_with_u8_array(data),
+        };
+        result.map_err(|e| HttpError::Network {
+            message: format!("Failed to send WebSocket message: {:?}", e),
+        })
+    }
+
+    fn broadcast(&self, message: WsMessage) {
+        self.subscribers
+            .borrow_mut()
+            .retain(|subscriber| subscriber.unbounded_send(message.clone()).is_ok());
+    }
+
+    fn flush_queue(&self) {
+        loop {
+            let Some(message) = self.queue.borrow_mut().pop_front() else {
+                break;
+            };
+            if self.send_now(&message).is_err() {
+                self.queue.borrow_mut().push_front(message);
+                break;
+            }
+        }
+    }
+}
+
+impl Drop for WsShared {
+    fn drop(&mut self) {
+        if let Some(handle) = self.socket.borrow_mut().take() {
+            handle.detach();
+        }
+    }
+}
+
+/// Open a socket and wire up its handlers
+fn open_socket(shared: &Rc<WsShared>) {
+    let socket = if shared.protocols.is_empty() {
+        web_sys::WebSocket::new(&shared.url)
+    } else {
+        let protocols: js_sys::Array = shared.protocols.iter().map(|p| wasm_bindgen::JsValue::from_str(p)).collect();
+        web_sys::WebSocket::new_with_str_sequence(&shared.url, &protocols)
+    };
+
+    let socket = match socket {
+        Ok(socket) => socket,
+        Err(_) => {
+            schedule_reconnect(shared);
+            return;
+        }
+    };
+    socket.set_binary_type(web_sys::BinaryType::Arraybuffer);
+
+    let weak = Rc::downgrade(shared);
+    let on_open = Closure::<dyn FnMut(web_sys::Event)>::new(move |_| {
+        let Some(shared) = weak.upgrade() else { return };
+        shared.attempts.set(0);
+        shared.set_state(WsState::Open);
+        shared.flush_queue();
+        start_heartbeat(&shared);
+    });
+
+    let weak = Rc::downgrade(shared);
+    let on_message = Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |event: web_sys::MessageEvent| {
+        let Some(shared) = weak.upgrade() else { return };
+        let data = event.data();
+        let message = match data.as_string() {
+            Some(text) => WsMessage::Text(text),
+            None => WsMessage::Binary(js_sys::Uint8Array::new(&data).to_vec()),
+        };
+        shared.broadcast(message);
+    });
+
+    let weak = Rc::downgrade(shared);
+    let on_close = Closure::<dyn FnMut(web_sys::CloseEvent)>::new(move |_event: web_sys::CloseEvent| {
+        let Some(shared) = weak.upgrade() else { return };
+        shared.heartbeat_timer.borrow_mut().take();
+        if shared.closed.get() {
+            shared.set_state(WsState::Closed);
+        } else {
+            schedule_reconnect(&shared);
+        }
+    });
+
+    socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+    // The previous socket is already closed; its handlers are no longer called
+    let previous = shared.socket.borrow_mut().replace(SocketHandle {
+        socket,
+        _on_open: on_open,
+        _on_message: on_message,
+        _on_close: on_close,
+    });
+    if let Some(previous) = previous {
+        // Drop outside the handler that may be running for it
+        wasm_bindgen_futures::spawn_local(async move { previous.detach() });
+    }
+}
+
+/// Wait with backoff and reconnect, or give up after too many attempts
+fn schedule_reconnect(shared: &Rc<WsShared>) {
+    let attempt = shared.attempts.get() + 1;
+    shared.attempts.set(attempt);
+
+    if shared.max_retries.is_some_and(|max| attempt > max) {
+        shared.set_state(WsState::Closed);
+        shared.subscribers.borrow_mut().clear();
+        return;
+    }
+
+    shared.set_state(if attempt == 1 && shared.state.get() == WsState::Connecting {
+        WsState::Connecting
+    } else {
+        WsState::Reconnecting
+    });
+
+    // Up to 20% jitter so that many clients do not reconnect in lockstep
+    let delay = backoff_delay(attempt, shared.initial_backoff_ms, shared.max_backoff_ms);
+    let jitter = delay as u64 * random::random_bytes::<1>()[0] as u64 / (255 * 5);
+    let delay = delay.saturating_add(jitter as u32);
+
+    let weak = Rc::downgrade(shared);
+    wasm_bindgen_futures::spawn_local(async move {
+        gloo_timers::future::TimeoutFuture::new(delay).await;
+        if let Some(shared) = weak.upgrade() {
+            if !shared.closed.get() {
+                open_socket(&shared);
+            }
+        }
+    });
+}
+
+fn start_heartbeat(shared: &Rc<WsShared>) {
+    let Some(ref heartbeat) = shared.heartbeat else { return };
+
+    let weak = Rc::downgrade(shared);
+    let timer = gloo_timers::callback::Interval::new(heartbeat.interval_ms, move || {
+        if let Some(shared) = weak.upgrade() {
+            if let Some(ref heartbeat) = shared.heartbeat {
+                let _ = shared.send_now(&heartbeat.message);
+            }
+        }
+    });
+    *shared.heartbeat_timer.borrow_mut() = Some(timer);
+}
+
+/// Builder for a [`WsClient`]
+pub struct WsBuilder {
+    url: Result<String, HttpError>,
+    protocols: Vec<String>,
+    initial_backoff_ms: u32,
+    max_backoff_ms: u32,
+    max_retries: Option<u32>,
+    max_queue: usize,
+    heartbeat: Option<Heartbeat>,
+    dispatch: Option<StoreDispatcher>,
+    with_loader: bool,
+    with_notifications: bool,
+    on_state_change: Option<Callback<WsState>>,
+}
+
+impl WsBuilder {
+    fn new(client: &HttpClient, path: &str) -> Self {
+        let page_url = web_sys::window().and_then(|window| window.location().href().ok());
+        Self {
+            url: websocket_url(&client.build_url(path), page_url.as_deref(), &client.allowed_origins),
+            protocols: Vec::new(),
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            max_retries: None,
+            max_queue: DEFAULT_MAX_QUEUE,
+            heartbeat: None,
+            dispatch: client.dispatch.clone(),
+            with_loader: false,
+            with_notifications: false,
+            on_state_change: None,
+        }
+    }
+
+    /// Request a subprotocol
+    pub fn protocol(mut self, protocol: &str) -> Self {
+        self.protocols.push(protocol.to_string());
+        self
+    }
+
+    /// Reconnect delays: `initial_ms` doubling per attempt up to `max_ms`
+    pub fn backoff(mut self, initial_ms: u32, max_ms: u32) -> Self {
+        self.initial_backoff_ms = initial_ms;
+        self.max_backoff_ms = max_ms.max(initial_ms);
+        self
+    }
+
+    /// Give up after `count` consecutive failed connection attempts
+    pub fn max_retries(mut self, count: u32) -> Self {
+        self.max_retries = Some(count);
+        self
+    }
+
+    /// Maximum number of messages queued while disconnected
+    pub fn max_queue(mut self, messages: usize) -> Self {
+        self.max_queue = messages;
+        self
+    }
+
+    /// Send `message` every `interval_ms` while connected
+    pub fn heartbeat(mut self, interval_ms: u32, message: WsMessage) -> Self {
+        self.heartbeat = Some(Heartbeat { interval_ms, message });
+        self
+    }
+
+    /// Show the loader while connecting or reconnecting
+    pub fn with_loader(mut self, enabled: bool) -> Self {
+        self.with_loader = enabled;
+        self
+    }
+
+    /// Show notifications when the connection drops, recovers or closes
+    pub fn with_notifications(mut self, enabled: bool) -> Self {
+        self.with_notifications = enabled;
+        self
+    }
+
+    /// Set a handler called whenever the connection state changes
+    pub fn on_state_change<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(WsState) + 'static,
+    {
+        self.on_state_change = Some(Callback::from(handler));
+        self
+    }
+
+    /// Open the connection
+    ///
+    /// Fails only if the URL is invalid; connection failures are retried.
+    pub fn connect(self) -> Result<WsClient, HttpError> {
+        let shared = Rc::new(WsShared {
+            url: self.url?,
+            protocols: self.protocols,
+            socket: RefCell::new(None),
+            state: Cell::new(WsState::Closed),
+            closed: Cell::new(false),
+            attempts: Cell::new(0),
+            queue: RefCell::new(VecDeque::new()),
+            max_queue: self.max_queue,
+            subscribers: RefCell::new(Vec::new()),
+            initial_backoff_ms: self.initial_backoff_ms,
+            max_backoff_ms: self.max_backoff_ms,
+            max_retries: self.max_retries,
+            heartbeat: self.heartbeat,
+            heartbeat_timer: RefCell::new(None),
+            dispatch: self.dispatch,
+            with_loader: self.with_loader,
+            with_notifications: self.with_notifications,
+            on_state_change: self.on_state_change,
+        });
+
+        shared.set_state(WsState::Connecting);
+        open_socket(&shared);
+        Ok(WsClient { shared })
+    }
+}
+
+/// Connected WebSocket client, shared by all clones
+///
+/// The connection is closed when the last clone is dropped.
+#[derive(Clone)]
+pub struct WsClient {
+    shared: Rc<WsShared>,
+}
+
+impl WsClient {
+    /// URL the client connects to
+    pub fn url(&self) -> &str {
+        &self.shared.url
+    }
+
+    /// Current connection state
+    pub fn state(&self) -> WsState {
+        self.shared.state.get()
+    }
+
+    /// Number of messages waiting for the connection to open
+    pub fn queued(&self) -> usize {
+        self.shared.queue.borrow().len()
+    }
+
+    /// Send a message, queueing it while disconnected
+    pub fn send(&self, message: WsMessage) -> Result<(), HttpError> {
+        let shared = &self.shared;
+        if shared.closed.get() || shared.state.get() == WsState::Closed {
+            return Err(HttpError::Network {
+                message: "WebSocket is closed".to_string(),
+            });
+        }
+
+        if shared.state.get() == WsState::Open && shared.queue.borrow().is_empty() && shared.send_now(&message).is_ok() {
+            return Ok(());
+        }
+
+        let mut queue = shared.queue.borrow_mut();
+        if queue.len() >= shared.max_queue {
+            return Err(HttpError::Network {
+                message: format!("WebSocket send queue is full ({} messages)", shared.max_queue),
+            });
+        }
+        queue.push_back(message);
+        Ok(())
+    }
+
+    /// Send a text message
+    pub fn send_text(&self, text: &str) -> Result<(), HttpError> {
+        self.send(WsMessage::Text(text.to_string()))
+    }
+
+    /// Send a binary message
+    pub fn send_binary(&self, data: Vec<u8>) -> Result<(), HttpError> {
+        self.send(WsMessage::Binary(data))
+    }
+
+    /// Serialize `value` as JSON and send it as a text message
+    pub fn send_json<T: Serialize>(&self, value: &T) -> Result<(), HttpError> {
+        let text = serde_json::to_string(value).map_err(|e| HttpError::Serialization {
+            message: format!("Failed to serialize WebSocket message: {}", e),
+        })?;
+        self.send(WsMessage::Text(text))
+    }
+
+    /// Subscribe to incoming messages
+    ///
+    /// Every subscriber receives every message from the time it subscribed.
+    /// The stream ends when the client is closed.
+    pub fn messages(&self) -> WsMessages {
+        let (sender, receiver) = mpsc::unbounded();
+        if self.shared.state.get() != WsState::Closed {
+            self.shared.subscribers.borrow_mut().push(sender);
+        }
+        WsMessages { receiver }
+    }
+
+    /// Close the connection and stop reconnecting
+    pub fn close(&self) {
+        self.shared.closed.set(true);
+        self.shared.heartbeat_timer.borrow_mut().take();
+        if let Some(handle) = self.shared.socket.borrow_mut().take() {
+            handle.detach();
+        }
+        self.shared.queue.borrow_mut().clear();
+        self.shared.subscribers.borrow_mut().clear();
+        self.shared.set_state(WsState::Closed);
+    }
+}
+
+/// Stream of incoming WebSocket messages
+pub struct WsMessages {
+    receiver: UnboundedReceiver<WsMessage>,
+}
+
+impl WsMessages {
+    /// Decode every message as JSON
+    pub fn json<T: DeserializeOwned + 'static>(self) -> LocalBoxStream<'static, Result<T, HttpError>> {
+        self.map(|message| message.json()).boxed_local()
+    }
+}
+
+impl Stream for WsMessages {
+    type Item = WsMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+impl HttpClient {
+    /// Prepare a WebSocket connection to `path`, resolved against the base URL
+    pub fn websocket(&self, path: &str) -> WsBuilder {
+        WsBuilder::new(self, path)
+    }
+}